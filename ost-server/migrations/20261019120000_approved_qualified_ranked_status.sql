@@ -0,0 +1,2 @@
+ALTER TYPE enum_ranked_status ADD VALUE 'approved';
+ALTER TYPE enum_ranked_status ADD VALUE 'qualified';
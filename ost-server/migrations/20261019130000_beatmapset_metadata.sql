@@ -0,0 +1,16 @@
+ALTER TABLE beatmaps
+    ADD COLUMN artist VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN artist_unicode VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN average_bpm REAL CHECK (average_bpm >= 0),
+    ADD COLUMN card_url TEXT NOT NULL DEFAULT '',
+    ADD COLUMN cover_url TEXT NOT NULL DEFAULT '',
+    ADD COLUMN creator VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN creator_id INTEGER NOT NULL DEFAULT 0 CHECK (creator_id >= 0),
+    ADD COLUMN genre VARCHAR(64),
+    ADD COLUMN language VARCHAR(64),
+    ADD COLUMN list_url TEXT NOT NULL DEFAULT '',
+    ADD COLUMN nsfw BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN source VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN tags TEXT,
+    ADD COLUMN title_unicode VARCHAR(255) NOT NULL DEFAULT '',
+    ADD COLUMN video BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1 @@
+ALTER TABLE moderators ADD COLUMN osu_id INTEGER UNIQUE CHECK (osu_id >= 1);
@@ -0,0 +1 @@
+ALTER TABLE beatmaps ADD COLUMN difficulty_name VARCHAR(255) NOT NULL DEFAULT '';
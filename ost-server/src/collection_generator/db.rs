@@ -1,25 +1,235 @@
-use crate::collection_generator::CollectionBeatmap;
+use crate::{
+    collection_generator::{reader::Reader, write_string_length, CollectionBeatmap},
+    ServerResult,
+};
 
 /// The latest osu! version supported by the generator.
 const OSU_VERSION: i32 = 20220406;
 
-/// Generates a binary representation of a collection database from a vector of collections and their associated beatmaps.
-pub fn generate_db(collections: Vec<(String, Vec<CollectionBeatmap>)>) -> Vec<u8> {
-    let mut buffer = OSU_VERSION.to_le_bytes().to_vec();
-    buffer.extend_from_slice(&(collections.len() as i32).to_le_bytes());
-    for (collection, beatmaps) in collections.iter() {
-        write_string(&mut buffer, collection);
-        buffer.extend_from_slice(&(beatmaps.len() as i32).to_le_bytes());
-        for beatmap in beatmaps {
-            write_string(&mut buffer, &beatmap.checksum);
+/// A collection of a `collection.db` file, which references its beatmaps by their MD5 checksums.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collection {
+    pub checksums: Vec<String>,
+    pub name: String,
+}
+
+impl From<(String, Vec<CollectionBeatmap>)> for Collection {
+    fn from((name, beatmaps): (String, Vec<CollectionBeatmap>)) -> Self {
+        Self {
+            checksums: beatmaps
+                .into_iter()
+                .map(|beatmap| beatmap.checksum)
+                .collect(),
+            name,
+        }
+    }
+}
+
+/// The contents of an osu! `collection.db` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollectionDatabase {
+    pub collections: Vec<Collection>,
+    /// The osu! version that wrote the file.
+    pub version: i32,
+}
+
+impl CollectionDatabase {
+    /// Creates a new [`CollectionDatabase`] with the latest supported osu! version.
+    pub fn new(collections: Vec<Collection>) -> Self {
+        Self {
+            collections,
+            version: OSU_VERSION,
+        }
+    }
+
+    /// Adds the `collections` next to the existing ones.
+    ///
+    /// The beatmaps of the collections whose name is already taken replace the beatmaps of the
+    /// existing collection if `replace` is set, or are otherwise added to them, skipping the
+    /// beatmaps it already contains.
+    pub fn merge(&mut self, collections: Vec<Collection>, replace: bool) {
+        for collection in collections {
+            let Some(existing) = self
+                .collections
+                .iter_mut()
+                .find(|existing| existing.name == collection.name)
+            else {
+                self.collections.push(collection);
+                continue;
+            };
+            if replace {
+                existing.checksums = collection.checksums;
+                continue;
+            }
+            for checksum in collection.checksums {
+                if !existing.checksums.contains(&checksum) {
+                    existing.checksums.push(checksum);
+                }
+            }
+        }
+    }
+
+    /// Parses the contents of a `collection.db` file.
+    ///
+    /// Returns [`Error::InvalidCollection`](crate::Error::InvalidCollection) if the `buffer` is not a valid `collection.db` file.
+    pub fn parse(buffer: &[u8]) -> ServerResult<Self> {
+        let mut reader = Reader::new(buffer);
+        let version = reader.read_i32()?;
+        let collections_count = reader.read_count()?;
+        let mut collections = Vec::new();
+        for _ in 0..collections_count {
+            let name = reader.read_osu_string()?;
+            let beatmaps_count = reader.read_count()?;
+            let mut checksums = Vec::new();
+            for _ in 0..beatmaps_count {
+                checksums.push(reader.read_osu_string()?);
+            }
+            collections.push(Collection { checksums, name });
+        }
+        reader.finish()?;
+        Ok(Self {
+            collections,
+            version,
+        })
+    }
+
+    /// Returns the binary representation of the collection database.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = self.version.to_le_bytes().to_vec();
+        buffer.extend_from_slice(&(self.collections.len() as i32).to_le_bytes());
+        for collection in &self.collections {
+            write_string(&mut buffer, &collection.name);
+            buffer.extend_from_slice(&(collection.checksums.len() as i32).to_le_bytes());
+            for checksum in &collection.checksums {
+                write_string(&mut buffer, checksum);
+            }
         }
+        buffer
     }
-    buffer
 }
 
-/// Writes a string to a given buffer in a specific binary format.
+/// Generates a binary representation of a collection database from a vector of collections and their associated beatmaps.
+pub fn generate_db(collections: Vec<(String, Vec<CollectionBeatmap>)>) -> Vec<u8> {
+    CollectionDatabase::new(collections.into_iter().map(Collection::from).collect()).to_bytes()
+}
+
+/// Writes a string to a given buffer as an osu! string prefixed by its ULEB128 encoded length.
 fn write_string(buffer: &mut Vec<u8>, string: &str) {
     buffer.push(11_u8);
-    buffer.push(string.len() as u8);
+    write_string_length(buffer, string.len());
     buffer.extend_from_slice(string.as_bytes());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Collection, CollectionDatabase, OSU_VERSION};
+    use crate::Error;
+    use tokio::fs;
+
+    fn collection(name: &str, checksums: &[&str]) -> Collection {
+        Collection {
+            checksums: checksums
+                .iter()
+                .map(|checksum| String::from(*checksum))
+                .collect(),
+            name: String::from(name),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_db() {
+        for file in ["./test_files/bpm.db", "./test_files/collection.db"] {
+            let buffer = fs::read(file).await.unwrap();
+            let database = CollectionDatabase::parse(&buffer).unwrap();
+            assert_eq!(database.version, OSU_VERSION);
+            assert_eq!(database.to_bytes(), buffer);
+        }
+        let database =
+            CollectionDatabase::parse(&fs::read("./test_files/bpm.db").await.unwrap()).unwrap();
+        assert_eq!(
+            database.collections,
+            [
+                collection("170-179", &["e4ad76f1a6b4e3bcfb1652d49159eff9"]),
+                collection("190-199", &["d6c8ba1406ad3de9381f51abf74be544"]),
+                collection("210-219", &["1ff6975c142ac59e4731cb09f5d46bcc"]),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_parse_invalid_db() {
+        let buffer = fs::read("./test_files/collection.db").await.unwrap();
+        for invalid in [
+            &buffer[..buffer.len() - 1],
+            &buffer[..3],
+            &[0, 0, 0, 0, 1, 0, 0, 0, 5],
+        ] {
+            assert!(matches!(
+                CollectionDatabase::parse(invalid),
+                Err(Error::InvalidCollection(_))
+            ));
+        }
+        let mut trailing = buffer.clone();
+        trailing.push(0);
+        assert!(CollectionDatabase::parse(&trailing).is_err());
+        let mut long_name = 1_i32.to_le_bytes().to_vec();
+        long_name.extend_from_slice(&1_i32.to_le_bytes());
+        long_name.extend_from_slice(&[11, 0x82, 0x01]);
+        long_name.extend_from_slice(&[b'a'; 130]);
+        long_name.extend_from_slice(&[0, 0, 0, 0]);
+        let database = CollectionDatabase::parse(&long_name).unwrap();
+        assert_eq!(database.collections[0].name.len(), 130);
+    }
+
+    #[tokio::test]
+    async fn test_write_long_names_db() {
+        let buffer = fs::read("./test_files/long_names.db").await.unwrap();
+        let database = CollectionDatabase::new(vec![
+            collection(
+                &"Stamina training ".repeat(18),
+                &["e4ad76f1a6b4e3bcfb1652d49159eff9"],
+            ),
+            collection(
+                "ストリーム練習 170-179 ★",
+                &[
+                    "e4ad76f1a6b4e3bcfb1652d49159eff9",
+                    "d6c8ba1406ad3de9381f51abf74be544",
+                ],
+            ),
+        ]);
+        assert_eq!(database.to_bytes(), buffer);
+        assert_eq!(CollectionDatabase::parse(&buffer).unwrap(), database);
+    }
+
+    #[test]
+    fn test_merge_db() {
+        let mut database = CollectionDatabase::new(vec![
+            collection("OST", &["a", "b"]),
+            collection("Favorites", &["c"]),
+        ]);
+        let mut replaced = database.clone();
+        database.merge(
+            vec![
+                collection("OST", &["b", "d"]),
+                collection("170-179", &["e"]),
+            ],
+            false,
+        );
+        assert_eq!(
+            database.collections,
+            [
+                collection("OST", &["a", "b", "d"]),
+                collection("Favorites", &["c"]),
+                collection("170-179", &["e"]),
+            ]
+        );
+        replaced.merge(vec![collection("OST", &["b", "d"])], true);
+        assert_eq!(
+            replaced.collections,
+            [
+                collection("OST", &["b", "d"]),
+                collection("Favorites", &["c"])
+            ]
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{
+    collection_generator::CollectionBeatmap, models::beatmap::RankedStatus, Error, ServerResult,
+};
+use serde::Deserialize;
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+/// The name of the only collection generated when the beatmaps are not grouped.
+const UNGROUPED_COLLECTION: &str = "OST";
+/// The separator between the names of the nested groups of a collection.
+const NESTED_SEPARATOR: &str = " / ";
+/// The suffix of the names of the collections of double time beatmaps.
+const DOUBLE_TIME_SUFFIX: &str = " +DT";
+/// The largest streams length of the beatmaps considered bursts, like `parseStreamsLength` of the
+/// bot (`ost-bot/src/models.ts`).
+const BURSTS_MAXIMUM: i16 = 8;
+/// The largest streams length of the beatmaps considered streams, longer ones are deathstreams,
+/// like `parseStreamsLength` of the bot (`ost-bot/src/models.ts`).
+const STREAMS_MAXIMUM: i16 = 24;
+/// The allowed widths of the BPM buckets.
+const BPM_WIDTHS: RangeInclusive<i16> = 1..=500;
+/// The allowed widths of the star rating buckets.
+const DIFFICULTY_RATING_WIDTHS: RangeInclusive<f32> = 0.1..=10.0;
+/// The allowed widths of the streams density buckets, as the names keep two decimals.
+const STREAMS_DENSITY_WIDTHS: RangeInclusive<f32> = 0.01..=1.0;
+
+/// A criterion to divide the beatmaps of a collection file into collections.
+///
+/// Several groupings are nested in order, e.g., `[Bpm { width: 10 }, StreamsLength]` generates
+/// collections such as `200-209 / Deathstreams`.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Grouping {
+    /// Buckets of `width` BPM, e.g., `200-209` for a width of 10.
+    Bpm {
+        width: i16,
+    },
+    /// Buckets of `width` stars, e.g., `5-5.5 stars` for a width of 0.5.
+    DifficultyRating {
+        width: f32,
+    },
+    RankedStatus,
+    /// Buckets of `width` streams density, e.g., `Density 0.4-0.5` for a width of 0.1.
+    StreamsDensity {
+        width: f32,
+    },
+    /// Bursts, streams or deathstreams, split at [`BURSTS_MAXIMUM`] and [`STREAMS_MAXIMUM`].
+    StreamsLength,
+}
+
+impl Grouping {
+    /// Returns the sorting key and the name of the group of the `beatmap`.
+    fn group(&self, beatmap: &CollectionBeatmap) -> (i32, String) {
+        match *self {
+            Self::Bpm { width } => {
+                let width = i32::from(width);
+                let start = i32::from(beatmap.bpm).div_euclid(width) * width;
+                (start, format!("{start}-{end}", end = start + width - 1))
+            }
+            Self::DifficultyRating { width } => {
+                let (bucket, range) = decimal_bucket(beatmap.difficulty_rating, width);
+                (bucket, format!("{range} stars"))
+            }
+            Self::RankedStatus => match beatmap.ranked_status {
+                RankedStatus::Ranked => (0, String::from("Ranked")),
+                RankedStatus::Approved => (1, String::from("Approved")),
+                RankedStatus::Qualified => (2, String::from("Qualified")),
+                RankedStatus::Loved => (3, String::from("Loved")),
+                RankedStatus::Unranked => (4, String::from("Unranked")),
+            },
+            Self::StreamsDensity { width } => {
+                let (bucket, range) = decimal_bucket(beatmap.streams_density, width);
+                (bucket, format!("Density {range}"))
+            }
+            Self::StreamsLength => match beatmap.streams_length {
+                length if length <= BURSTS_MAXIMUM => (0, String::from("Bursts")),
+                length if length <= STREAMS_MAXIMUM => (1, String::from("Streams")),
+                _ => (2, String::from("Deathstreams")),
+            },
+        }
+    }
+
+    /// Returns [`Error::InvalidGrouping`] if the width of the buckets is out of its allowed range.
+    fn validate(&self) -> ServerResult<()> {
+        let is_valid = match *self {
+            Self::Bpm { width } => BPM_WIDTHS.contains(&width),
+            Self::DifficultyRating { width } => DIFFICULTY_RATING_WIDTHS.contains(&width),
+            Self::StreamsDensity { width } => STREAMS_DENSITY_WIDTHS.contains(&width),
+            Self::RankedStatus | Self::StreamsLength => true,
+        };
+        if !is_valid {
+            return Err(Error::InvalidGrouping(format!(
+                "Bucket width out of range in {self:?}"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Returns [`Error::InvalidGrouping`] if the width of the buckets of any of the `groupings` is out
+/// of its allowed range.
+pub fn validate_groupings(groupings: &[Grouping]) -> ServerResult<()> {
+    for grouping in groupings {
+        grouping.validate()?;
+    }
+    Ok(())
+}
+
+/// Returns the collections of beatmaps grouped by the nested `groupings`, sorted by their groups.
+///
+/// The beatmaps are gathered into a single collection if there are no groupings, and the double
+/// time beatmaps are kept apart in collections suffixed by `+DT`, right after the collection of
+/// the same group without modifications.
+///
+/// # Arguments
+///
+/// * `beatmaps` - A collection of beatmaps.
+/// * `groupings` - The criteria to divide the beatmaps into collections, from outermost to
+///   innermost.
+pub fn group_beatmaps(
+    beatmaps: Vec<CollectionBeatmap>,
+    groupings: &[Grouping],
+) -> ServerResult<Vec<(String, Vec<CollectionBeatmap>)>> {
+    validate_groupings(groupings)?;
+    if groupings.is_empty() && beatmaps.iter().all(|beatmap| !beatmap.double_time) {
+        return Ok(vec![(String::from(UNGROUPED_COLLECTION), beatmaps)]);
+    }
+    let mut collections = BTreeMap::<Vec<i32>, (String, Vec<CollectionBeatmap>)>::new();
+    for beatmap in beatmaps {
+        let (mut key, names): (Vec<_>, Vec<_>) = groupings
+            .iter()
+            .map(|grouping| grouping.group(&beatmap))
+            .unzip();
+        key.push(i32::from(beatmap.double_time));
+        collections
+            .entry(key)
+            .or_insert_with(|| {
+                let mut name = if names.is_empty() {
+                    String::from(UNGROUPED_COLLECTION)
+                } else {
+                    names.join(NESTED_SEPARATOR)
+                };
+                if beatmap.double_time {
+                    name.push_str(DOUBLE_TIME_SUFFIX);
+                }
+                (name, Vec::new())
+            })
+            .1
+            .push(beatmap);
+    }
+    Ok(collections.into_values().collect())
+}
+
+/// Returns the bucket of `width` containing the `value` and its range, e.g., `5-5.5`.
+fn decimal_bucket(value: f32, width: f32) -> (i32, String) {
+    let bucket = (value / width).floor() as i32;
+    let start = bucket as f32 * width;
+    (
+        bucket,
+        format!(
+            "{}-{}",
+            format_decimal(start),
+            format_decimal(start + width)
+        ),
+    )
+}
+
+/// Formats the `value` with up to two decimals, without trailing zeros.
+fn format_decimal(value: f32) -> String {
+    let formatted = format!("{value:.2}");
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{group_beatmaps, Grouping};
+    use crate::{collection_generator::CollectionBeatmap, models::beatmap::RankedStatus};
+
+    fn beatmap(
+        bpm: i16,
+        difficulty_rating: f32,
+        ranked_status: RankedStatus,
+        streams_density: f32,
+        streams_length: i16,
+    ) -> CollectionBeatmap {
+        CollectionBeatmap {
+            artist: String::new(),
+            beatmapset_id: 1,
+            bpm,
+            checksum: String::new(),
+            difficulty_name: String::new(),
+            difficulty_rating,
+            double_time: false,
+            id: i32::from(bpm),
+            ranked_status,
+            streams_density,
+            streams_length,
+            title: String::new(),
+        }
+    }
+
+    fn names(collections: &[(String, Vec<CollectionBeatmap>)]) -> Vec<&str> {
+        collections.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    fn setup_test() -> Vec<CollectionBeatmap> {
+        vec![
+            beatmap(205, 6.2, RankedStatus::Ranked, 0.45, 30),
+            beatmap(95, 4.9, RankedStatus::Loved, 0.12, 5),
+            beatmap(201, 5.7, RankedStatus::Approved, 0.61, 12),
+            beatmap(180, 5.1, RankedStatus::Ranked, 0.3, 25),
+        ]
+    }
+
+    #[test]
+    fn test_group_beatmaps() {
+        let collections = group_beatmaps(setup_test(), &[]).unwrap();
+        assert_eq!(names(&collections), ["OST"]);
+        assert_eq!(collections[0].1.len(), 4);
+        let collections = group_beatmaps(setup_test(), &[Grouping::Bpm { width: 10 }]).unwrap();
+        assert_eq!(names(&collections), ["90-99", "180-189", "200-209"]);
+        assert_eq!(collections[2].1.len(), 2);
+        let collections = group_beatmaps(setup_test(), &[Grouping::Bpm { width: 25 }]).unwrap();
+        assert_eq!(names(&collections), ["75-99", "175-199", "200-224"]);
+        let collections =
+            group_beatmaps(setup_test(), &[Grouping::DifficultyRating { width: 0.5 }]).unwrap();
+        assert_eq!(
+            names(&collections),
+            ["4.5-5 stars", "5-5.5 stars", "5.5-6 stars", "6-6.5 stars"]
+        );
+        let collections =
+            group_beatmaps(setup_test(), &[Grouping::StreamsDensity { width: 0.25 }]).unwrap();
+        assert_eq!(
+            names(&collections),
+            ["Density 0-0.25", "Density 0.25-0.5", "Density 0.5-0.75"]
+        );
+        let collections = group_beatmaps(setup_test(), &[Grouping::RankedStatus]).unwrap();
+        assert_eq!(names(&collections), ["Ranked", "Approved", "Loved"]);
+        let collections = group_beatmaps(setup_test(), &[Grouping::StreamsLength]).unwrap();
+        assert_eq!(names(&collections), ["Bursts", "Streams", "Deathstreams"]);
+    }
+
+    #[test]
+    fn test_nested_group_beatmaps() {
+        let collections = group_beatmaps(
+            setup_test(),
+            &[Grouping::Bpm { width: 10 }, Grouping::StreamsLength],
+        )
+        .unwrap();
+        assert_eq!(
+            names(&collections),
+            [
+                "90-99 / Bursts",
+                "180-189 / Deathstreams",
+                "200-209 / Streams",
+                "200-209 / Deathstreams"
+            ]
+        );
+        for invalid in [
+            Grouping::Bpm { width: 0 },
+            Grouping::Bpm { width: i16::MAX },
+            Grouping::DifficultyRating { width: f32::NAN },
+            Grouping::DifficultyRating { width: 0.0001 },
+            Grouping::StreamsDensity { width: 0.0001 },
+            Grouping::StreamsDensity {
+                width: f32::INFINITY,
+            },
+        ] {
+            assert!(group_beatmaps(setup_test(), &[invalid]).is_err());
+        }
+        let collections = group_beatmaps(
+            vec![beatmap(i16::MAX, 5.0, RankedStatus::Ranked, 0.5, 10)],
+            &[Grouping::Bpm { width: 500 }],
+        )
+        .unwrap();
+        assert_eq!(names(&collections), ["32500-32999"]);
+        let groupings: Vec<Grouping> =
+            serde_json::from_str(r#"[{"type": "bpm", "width": 10}, {"type": "streams_length"}]"#)
+                .unwrap();
+        assert_eq!(
+            groupings,
+            [Grouping::Bpm { width: 10 }, Grouping::StreamsLength]
+        );
+    }
+
+    #[test]
+    fn test_double_time_group_beatmaps() {
+        let beatmaps = || {
+            let mut beatmaps = setup_test();
+            for mut double_time in [
+                beatmap(267, 7.4, RankedStatus::Ranked, 0.45, 30),
+                beatmap(203, 6.9, RankedStatus::Loved, 0.12, 5),
+            ] {
+                double_time.double_time = true;
+                beatmaps.push(double_time);
+            }
+            beatmaps
+        };
+        let collections = group_beatmaps(beatmaps(), &[]).unwrap();
+        assert_eq!(names(&collections), ["OST", "OST +DT"]);
+        assert_eq!(collections[1].1.len(), 2);
+        let collections = group_beatmaps(beatmaps(), &[Grouping::Bpm { width: 10 }]).unwrap();
+        assert_eq!(
+            names(&collections),
+            ["90-99", "180-189", "200-209", "200-209 +DT", "260-269 +DT"]
+        );
+        assert!(collections[3].1.iter().all(|beatmap| beatmap.double_time));
+    }
+}
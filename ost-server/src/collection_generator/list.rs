@@ -0,0 +1,224 @@
+use crate::ServerResult;
+use axum::body::Bytes;
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+/// The URL of a beatmap on the osu! website, followed by its ID.
+const BEATMAP_URL: &str = "https://osu.ppy.sh/b/";
+/// The URL of a beatmapset on the osu! website, followed by its ID.
+const BEATMAPSET_URL: &str = "https://osu.ppy.sh/s/";
+/// The header of the `.csv` lists, in the same order as the written fields.
+const CSV_HEADER: &str = "id,beatmapset_id,title,bpm,difficulty_rating,double_time,\
+    longest_stream,streams_density,streams_length,streams_spacing,url,beatmapset_url\n";
+
+/// The text formats a filtered set of beatmaps can be exported as, written one beatmap at a time.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ListFormat {
+    Csv,
+    Json,
+    /// A plain list of beatmap links for download managers, one per line.
+    Links,
+}
+
+impl ListFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Links => "text/plain; charset=utf-8",
+        }
+    }
+
+    fn footer(self) -> &'static str {
+        match self {
+            Self::Json => "]",
+            Self::Csv | Self::Links => "",
+        }
+    }
+
+    fn header(self) -> &'static str {
+        match self {
+            Self::Csv => CSV_HEADER,
+            Self::Json => "[",
+            Self::Links => "",
+        }
+    }
+
+    /// Returns the representation of the `beatmap` in the list, where `is_first` indicates
+    /// whether it is the first beatmap written.
+    fn row(self, beatmap: &ListBeatmap, is_first: bool) -> ServerResult<Bytes> {
+        Ok(Bytes::from(match self {
+            Self::Csv => format!(
+                "{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                beatmap.id,
+                beatmap.beatmapset_id,
+                escape_csv(&beatmap.title),
+                beatmap.bpm,
+                beatmap.difficulty_rating,
+                beatmap.double_time,
+                beatmap.longest_stream,
+                beatmap.streams_density,
+                beatmap.streams_length,
+                beatmap.streams_spacing,
+                beatmap.url(),
+                beatmap.beatmapset_url()
+            ),
+            Self::Json => {
+                let entry = serde_json::to_string(&ListEntry {
+                    beatmap,
+                    beatmapset_url: beatmap.beatmapset_url(),
+                    url: beatmap.url(),
+                })?;
+                if is_first {
+                    entry
+                } else {
+                    format!(",{entry}")
+                }
+            }
+            Self::Links => format!("{}\n", beatmap.url()),
+        }))
+    }
+}
+
+/// Represents the information of a beatmap written into the exported lists.
+#[derive(FromRow, Serialize)]
+pub struct ListBeatmap {
+    pub beatmapset_id: i32,
+    pub bpm: i16,
+    pub difficulty_rating: f32,
+    /// Whether the statistics of the beatmap are the ones with double time.
+    pub double_time: bool,
+    pub id: i32,
+    pub longest_stream: i16,
+    pub streams_density: f32,
+    pub streams_length: i16,
+    pub streams_spacing: f32,
+    pub title: String,
+}
+
+impl ListBeatmap {
+    fn beatmapset_url(&self) -> String {
+        format!("{BEATMAPSET_URL}{}", self.beatmapset_id)
+    }
+
+    fn url(&self) -> String {
+        format!("{BEATMAP_URL}{}", self.id)
+    }
+}
+
+/// A beatmap of the `.json` lists along with its links.
+#[derive(Serialize)]
+struct ListEntry<'a> {
+    #[serde(flatten)]
+    beatmap: &'a ListBeatmap,
+    beatmapset_url: String,
+    url: String,
+}
+
+/// Returns the chunks of a list in the `format` written as the `beatmaps` are streamed.
+///
+/// # Arguments
+///
+/// * `beatmaps` - A stream of beatmaps.
+/// * `format` - The format of the list.
+pub fn generate_list(
+    beatmaps: impl Stream<Item = ServerResult<ListBeatmap>>,
+    format: ListFormat,
+) -> impl Stream<Item = ServerResult<Bytes>> {
+    stream::once(async move { Ok(Bytes::from_static(format.header().as_bytes())) })
+        .chain(
+            beatmaps
+                .enumerate()
+                .map(move |(index, beatmap)| format.row(&beatmap?, index == 0)),
+        )
+        .chain(stream::once(async move {
+            Ok(Bytes::from_static(format.footer().as_bytes()))
+        }))
+}
+
+/// Quotes a field of a `.csv` list if it contains separators, quotes or line breaks.
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_list, ListBeatmap, ListFormat};
+    use futures::{stream, TryStreamExt};
+
+    fn setup_test() -> Vec<ListBeatmap> {
+        vec![
+            ListBeatmap {
+                beatmapset_id: 128931,
+                bpm: 175,
+                difficulty_rating: 5.14,
+                double_time: false,
+                id: 847314,
+                longest_stream: 17,
+                streams_density: 0.4,
+                streams_length: 10,
+                streams_spacing: 1.5,
+                title: String::from("Title, \"Live\" [Insane]"),
+            },
+            ListBeatmap {
+                beatmapset_id: 933630,
+                bpm: 315,
+                difficulty_rating: 9.2,
+                double_time: true,
+                id: 1949106,
+                longest_stream: 64,
+                streams_density: 0.6,
+                streams_length: 30,
+                streams_spacing: 2.0,
+                title: String::from("Title [Expert]"),
+            },
+        ]
+    }
+
+    async fn list(beatmaps: Vec<ListBeatmap>, format: ListFormat) -> String {
+        let chunks: Vec<_> = generate_list(stream::iter(beatmaps.into_iter().map(Ok)), format)
+            .try_collect()
+            .await
+            .unwrap();
+        String::from_utf8(chunks.concat()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_generate_list() {
+        assert_eq!(
+            list(setup_test(), ListFormat::Links).await,
+            "https://osu.ppy.sh/b/847314\nhttps://osu.ppy.sh/b/1949106\n"
+        );
+        let csv = list(setup_test(), ListFormat::Csv).await;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("id,beatmapset_id,title,bpm"));
+        assert_eq!(
+            lines[1],
+            "847314,128931,\"Title, \"\"Live\"\" [Insane]\",175,5.14,false,17,0.4,10,1.5,\
+                https://osu.ppy.sh/b/847314,https://osu.ppy.sh/s/128931"
+        );
+        let json: serde_json::Value =
+            serde_json::from_str(&list(setup_test(), ListFormat::Json).await).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["title"], "Title, \"Live\" [Insane]");
+        assert_eq!(json[1]["double_time"], true);
+        assert_eq!(json[1]["url"], "https://osu.ppy.sh/b/1949106");
+        assert_eq!(json[1]["beatmapset_url"], "https://osu.ppy.sh/s/933630");
+        assert_eq!(list(Vec::new(), ListFormat::Json).await, "[]");
+        assert_eq!(
+            list(Vec::new(), ListFormat::Csv)
+                .await
+                .lines()
+                .collect::<Vec<_>>()
+                .len(),
+            1
+        );
+    }
+}
@@ -1,22 +1,35 @@
 mod db;
+mod grouping;
+mod list;
 mod osdb;
+mod reader;
 
-use crate::ServerResult;
-use db::generate_db;
-use osdb::generate_osdb;
+use crate::{models::beatmap::RankedStatus, ServerResult};
+use db::{generate_db, Collection, CollectionDatabase};
+use grouping::group_beatmaps;
+pub use grouping::{validate_groupings, Grouping};
+pub use list::{generate_list, ListBeatmap, ListFormat};
+use osdb::{generate_osdb, OsdbCollection, OsdbFile};
 use sqlx::FromRow;
-use std::collections::HashMap;
 
 /// Represents the information required to store a beatmap into a `.db` or a
 /// `.osdb` file.
 #[derive(FromRow)]
 pub struct CollectionBeatmap {
+    pub artist: String,
     pub beatmapset_id: i32,
     pub bpm: i16,
     pub checksum: String,
+    pub difficulty_name: String,
     pub difficulty_rating: f32,
+    /// Whether the statistics of the beatmap are the ones with double time.
+    pub double_time: bool,
     pub id: i32,
+    pub ranked_status: RankedStatus,
+    pub streams_density: f32,
     pub streams_length: i16,
+    /// The title of the beatmapset followed by the difficulty name in brackets.
+    pub title: String,
 }
 
 /// Returns a `.osdb` or a `.db` buffer generated from a collection of
@@ -25,86 +38,158 @@ pub struct CollectionBeatmap {
 /// # Arguments
 ///
 /// * `beatmaps` - A collection of beatmaps.
-/// * `use_bpm_division` - An indicator of whether to use bpm division for the collections.
+/// * `groupings` - The nested criteria to divide the beatmaps into collections.
 /// * `generate_osdb` - An indicator of whether to generate a `.osdb` or a `.db`
 ///   buffer.
+/// * `use_osdb_metadata` - An indicator of whether to include the artist, title and difficulty
+///   name of the beatmaps in the `.osdb` buffer.
 pub async fn generate_collection_file(
     beatmaps: Vec<CollectionBeatmap>,
-    use_bpm_division: bool,
+    groupings: &[Grouping],
     use_osdb_format: bool,
+    use_osdb_metadata: bool,
 ) -> ServerResult<Vec<u8>> {
-    let mut collections = if use_bpm_division {
-        generate_bpm_collection_file(beatmaps)
-    } else {
-        Vec::<(String, Vec<CollectionBeatmap>)>::from([(String::from("OST"), beatmaps)])
-    };
-    collections.sort_by(|(name_a, _), (name_b, _)| name_a.cmp(name_b));
+    let collections = group_beatmaps(beatmaps, groupings)?;
     if use_osdb_format {
-        Ok(generate_osdb(collections).await?)
+        Ok(generate_osdb(collections, use_osdb_metadata).await?)
     } else {
         Ok(generate_db(collections))
     }
 }
 
-/// Returns a set of collections of beatmaps grouped its bpm.
-///
-/// # Arguments
-///
-/// * `beatmaps` - A collection of beatmaps.
-fn generate_bpm_collection_file(
-    beatmaps: Vec<CollectionBeatmap>,
-) -> Vec<(String, Vec<CollectionBeatmap>)> {
-    let mut divisions = HashMap::<i16, Vec<CollectionBeatmap>>::new();
-    for beatmap in beatmaps {
-        let bpm = beatmap.bpm / 10;
-        if let Some(division) = divisions.get_mut(&bpm) {
-            division.push(beatmap);
+/// An existing `.osdb` or `collection.db` file into which generated collections are merged.
+pub enum CollectionFile {
+    Database(CollectionDatabase),
+    Osdb(OsdbFile),
+}
+
+impl CollectionFile {
+    /// Parses the contents of an existing `.osdb` or `collection.db` file.
+    ///
+    /// Returns [`Error::InvalidCollection`](crate::Error::InvalidCollection) if the
+    /// `collection_file` is not a valid `.osdb` or `collection.db` file.
+    pub async fn parse(collection_file: &[u8]) -> ServerResult<Self> {
+        if OsdbFile::is_osdb(collection_file) {
+            Ok(Self::Osdb(OsdbFile::parse(collection_file).await?))
         } else {
-            divisions.insert(bpm, Vec::from([beatmap]));
+            Ok(Self::Database(CollectionDatabase::parse(collection_file)?))
+        }
+    }
+
+    /// Returns a `.osdb` or a `.db` buffer with the collections generated from a collection of
+    /// beatmaps merged into the file, keeping its format.
+    ///
+    /// # Arguments
+    ///
+    /// * `beatmaps` - A collection of beatmaps.
+    /// * `groupings` - The nested criteria to divide the beatmaps into collections.
+    /// * `replace` - An indicator of whether to replace the beatmaps of the existing collections
+    ///   with the same name as the generated ones, instead of adding to them.
+    pub async fn merge(
+        self,
+        beatmaps: Vec<CollectionBeatmap>,
+        groupings: &[Grouping],
+        replace: bool,
+    ) -> ServerResult<Vec<u8>> {
+        let collections = group_beatmaps(beatmaps, groupings)?;
+        match self {
+            Self::Database(mut database) => {
+                database.merge(
+                    collections.into_iter().map(Collection::from).collect(),
+                    replace,
+                );
+                Ok(database.to_bytes())
+            }
+            Self::Osdb(mut osdb) => {
+                osdb.merge(
+                    collections.into_iter().map(OsdbCollection::from).collect(),
+                    replace,
+                );
+                osdb.to_bytes().await
+            }
         }
     }
-    let mut collections = Vec::<(String, Vec<CollectionBeatmap>)>::with_capacity(divisions.len());
-    for (bpm, beatmaps) in divisions {
-        collections.push((
-            format!(
-                "{start}-{end}",
-                start = bpm * 10,
-                end = ((bpm + 1) * 10) - 1
-            ),
-            beatmaps,
-        ));
+}
+
+/// Writes the `length` of a string into a collection file buffer as an unsigned LEB128 integer,
+/// which is the encoding of both osu! strings and .NET `BinaryWriter` strings.
+///
+/// # Arguments
+///
+/// * `buffer` - A previously initialized collection file buffer.
+/// * `length` - The length in bytes of a string.
+fn write_string_length(buffer: &mut Vec<u8>, mut length: usize) {
+    while length >= 0x80 {
+        buffer.push((length as u8 & 0x7f) | 0x80);
+        length >>= 7;
     }
-    collections
+    buffer.push(length as u8);
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::collection_generator::{generate_collection_file, CollectionBeatmap};
+    use crate::collection_generator::{
+        generate_collection_file, osdb::OsdbFile, CollectionBeatmap, CollectionFile, Grouping,
+    };
+    use crate::models::beatmap::RankedStatus;
+    use crate::ServerResult;
     use tokio::fs;
 
+    const BPM_GROUPING: &[Grouping] = &[Grouping::Bpm { width: 10 }];
+
+    async fn merge_collection_file(
+        beatmaps: Vec<CollectionBeatmap>,
+        collection_file: &[u8],
+        groupings: &[Grouping],
+        replace: bool,
+    ) -> ServerResult<Vec<u8>> {
+        CollectionFile::parse(collection_file)
+            .await?
+            .merge(beatmaps, groupings, replace)
+            .await
+    }
+
     fn setup_test() -> Vec<CollectionBeatmap> {
         vec![
             CollectionBeatmap {
+                artist: String::from("Artist 1"),
+                difficulty_name: String::from("Insane"),
+                title: String::from("Title 1 [Insane]"),
                 id: 847314,
+                ranked_status: RankedStatus::Ranked,
+                streams_density: 0.4,
                 beatmapset_id: 128931,
                 bpm: 175,
                 streams_length: 10,
+                double_time: false,
                 difficulty_rating: 5.14,
                 checksum: String::from("e4ad76f1a6b4e3bcfb1652d49159eff9"),
             },
             CollectionBeatmap {
+                artist: String::from("Artist 2"),
+                difficulty_name: String::from("Extra"),
+                title: String::from("Title 2 [Extra]"),
                 id: 476149,
+                ranked_status: RankedStatus::Ranked,
+                streams_density: 0.2,
                 beatmapset_id: 153776,
                 bpm: 190,
                 streams_length: 5,
+                double_time: false,
                 difficulty_rating: 4.88,
                 checksum: String::from("d6c8ba1406ad3de9381f51abf74be544"),
             },
             CollectionBeatmap {
+                artist: String::from("Artist 3"),
+                difficulty_name: String::from("Expert"),
+                title: String::from("Title 3 [Expert]"),
                 id: 1949106,
+                ranked_status: RankedStatus::Loved,
+                streams_density: 0.6,
                 beatmapset_id: 933630,
                 bpm: 210,
                 streams_length: 30,
+                double_time: false,
                 difficulty_rating: 7.49,
                 checksum: String::from("1ff6975c142ac59e4731cb09f5d46bcc"),
             },
@@ -113,7 +198,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_db_collection_by_bpm() {
-        let collection = generate_collection_file(setup_test(), true, false)
+        let collection = generate_collection_file(setup_test(), BPM_GROUPING, false, false)
             .await
             .unwrap();
         assert_eq!(&collection, &fs::read("./test_files/bpm.db").await.unwrap());
@@ -121,7 +206,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_db_collection() {
-        let collection = generate_collection_file(setup_test(), false, false)
+        let collection = generate_collection_file(setup_test(), &[], false, false)
             .await
             .unwrap();
         assert_eq!(
@@ -130,9 +215,49 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_merge_db_collection() {
+        let collection = fs::read("./test_files/collection.db").await.unwrap();
+        let merged = merge_collection_file(setup_test(), &collection, &[], false)
+            .await
+            .unwrap();
+        assert_eq!(&merged, &collection);
+        let merged = merge_collection_file(setup_test(), &collection, BPM_GROUPING, true)
+            .await
+            .unwrap();
+        let by_bpm = fs::read("./test_files/bpm.db").await.unwrap();
+        assert_eq!(merged.len(), collection.len() + by_bpm.len() - 8);
+        assert_eq!(&merged[..4], &collection[..4]);
+        assert_eq!(merged[4], 4);
+        assert!(
+            merge_collection_file(setup_test(), &collection[1..], &[], false)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_merge_osdb_collection() {
+        let collection = fs::read("./test_files/collection.osdb").await.unwrap();
+        let merged = merge_collection_file(setup_test(), &collection, &[], false)
+            .await
+            .unwrap();
+        assert_eq!(&merged, &collection);
+        let merged = merge_collection_file(setup_test(), &collection, BPM_GROUPING, true)
+            .await
+            .unwrap();
+        assert_ne!(&merged, &collection);
+        assert_eq!(&merged[..9], &collection[..9]);
+        let mut truncated = collection.clone();
+        truncated.pop();
+        assert!(merge_collection_file(setup_test(), &truncated, &[], false)
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_create_osdb_collection_by_bpm() {
-        let collection = generate_collection_file(setup_test(), true, true)
+        let collection = generate_collection_file(setup_test(), BPM_GROUPING, true, false)
             .await
             .unwrap();
         assert_eq!(
@@ -141,9 +266,25 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_create_osdb_collection_with_metadata() {
+        let collection = generate_collection_file(setup_test(), &[], true, true)
+            .await
+            .unwrap();
+        let osdb = OsdbFile::parse(&collection).await.unwrap();
+        assert_eq!(osdb.version.name(), "o!dm8");
+        let metadata = osdb.collections[0].beatmaps[0].metadata.as_ref().unwrap();
+        assert_eq!(metadata.artist, "Artist 1");
+        assert_eq!(metadata.difficulty_name, "Insane");
+        assert_eq!(metadata.title, "Title 1");
+        assert_eq!(osdb.collections[0].beatmaps[0].mode, 0);
+        let minimal = fs::read("./test_files/collection.osdb").await.unwrap();
+        assert!(collection.len() > minimal.len());
+    }
+
     #[tokio::test]
     async fn test_create_osdb_collection() {
-        let collection = generate_collection_file(setup_test(), false, true)
+        let collection = generate_collection_file(setup_test(), &[], true, false)
             .await
             .unwrap();
         assert_eq!(
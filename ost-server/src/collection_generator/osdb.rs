@@ -1,13 +1,370 @@
-use crate::{collection_generator::CollectionBeatmap, ServerResult};
-use async_compression::tokio::write::GzipEncoder;
-use tokio::io::AsyncWriteExt;
+use crate::{
+    collection_generator::{reader::Reader, write_string_length, CollectionBeatmap},
+    Error, ServerResult,
+};
+use async_compression::tokio::{bufread::GzipDecoder, write::GzipEncoder};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-/// The latest `.osdb` version supported by the generator.
-const OSDB_VERSION: &str = "o!dm8min";
+/// The `.osdb` version written by the generator.
+const OSDB_VERSION: OsdbVersion = OsdbVersion {
+    minimal: true,
+    number: 8,
+};
+/// The `.osdb` version written by the generator when including the metadata of the beatmaps.
+const OSDB_METADATA_VERSION: OsdbVersion = OsdbVersion {
+    minimal: false,
+    number: 8,
+};
+/// The largest decompressed size of an uploaded `.osdb` file, guarding against compression bombs.
+const MAX_DECOMPRESSED: u64 = 256 * 1024 * 1024;
 /// The required footer for the `.osdb` files.
 const FOOTER: &str = "By Piotrekol";
 /// The creator of the collections sets.
 const CREATOR: &str = "Sombrax79";
+/// The play mode of the beatmaps of the generated collections, osu!standard.
+const PLAY_MODE: u8 = 0;
+/// The play mode written for the beatmaps of the generated `o!dm8min` files, which has always been
+/// `1` and is kept so that the minimal exports stay byte for byte identical to the earlier ones.
+const MINIMAL_PLAY_MODE: u8 = 1;
+
+/// A version of the `.osdb` format written by Collection Manager, from `o!dm6` to `o!dm8`.
+///
+/// The minimal variants omit the artist, title and difficulty name of the beatmaps, and the
+/// versions since `o!dm7` are compressed with gzip.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct OsdbVersion {
+    pub minimal: bool,
+    pub number: u8,
+}
+
+impl OsdbVersion {
+    /// Returns the [`OsdbVersion`] with the specified `name`, e.g., `o!dm8min`.
+    ///
+    /// Returns [`Error::InvalidCollection`] if the version is not supported.
+    pub fn from_name(name: &str) -> ServerResult<Self> {
+        let number = name
+            .strip_prefix("o!dm")
+            .ok_or_else(|| Error::InvalidCollection(format!("Unknown .osdb version {name}")))?;
+        let (number, minimal) = match number.strip_suffix("min") {
+            Some(number) => (number, true),
+            None => (number, false),
+        };
+        match number.parse() {
+            Ok(number @ 6..=8) => Ok(Self { minimal, number }),
+            _ => Err(Error::InvalidCollection(format!(
+                "Unsupported .osdb version {name}"
+            ))),
+        }
+    }
+
+    /// Returns whether the play mode and star rating of the beatmaps are stored, which the minimal
+    /// variants only do since `o!dm8`.
+    fn has_difficulty(&self) -> bool {
+        !self.minimal || self.number >= 8
+    }
+
+    fn is_compressed(&self) -> bool {
+        self.number >= 7
+    }
+
+    pub fn name(&self) -> String {
+        format!(
+            "o!dm{}{}",
+            self.number,
+            if self.minimal { "min" } else { "" }
+        )
+    }
+}
+
+/// The metadata of a beatmap, stored only by the full `.osdb` formats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OsdbBeatmapMetadata {
+    pub artist: String,
+    pub difficulty_name: String,
+    pub title: String,
+}
+
+/// A beatmap of a `.osdb` collection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsdbBeatmap {
+    pub beatmapset_id: i32,
+    pub checksum: String,
+    pub comment: String,
+    /// The star rating of the beatmap, or `0` if the version does not store it.
+    pub difficulty_rating: f64,
+    pub id: i32,
+    /// The metadata of the beatmap, which is only read and written by the full formats.
+    pub metadata: Option<OsdbBeatmapMetadata>,
+    /// The play mode of the beatmap, or `0` if the version does not store it.
+    pub mode: u8,
+}
+
+impl From<CollectionBeatmap> for OsdbBeatmap {
+    fn from(beatmap: CollectionBeatmap) -> Self {
+        let title = beatmap
+            .title
+            .strip_suffix(&format!(" [{}]", beatmap.difficulty_name))
+            .map(String::from)
+            .unwrap_or(beatmap.title);
+        Self {
+            beatmapset_id: beatmap.beatmapset_id,
+            checksum: beatmap.checksum,
+            comment: String::new(),
+            difficulty_rating: beatmap.difficulty_rating as f64,
+            id: beatmap.id,
+            metadata: Some(OsdbBeatmapMetadata {
+                artist: beatmap.artist,
+                difficulty_name: beatmap.difficulty_name,
+                title,
+            }),
+            mode: PLAY_MODE,
+        }
+    }
+}
+
+/// A collection of a `.osdb` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OsdbCollection {
+    pub beatmaps: Vec<OsdbBeatmap>,
+    /// The MD5 checksums of the beatmaps of the collection without any other information.
+    pub checksums: Vec<String>,
+    pub name: String,
+    /// The ID of the collection in osustats, or `-1` if it was not uploaded.
+    pub online_id: i32,
+}
+
+impl From<(String, Vec<CollectionBeatmap>)> for OsdbCollection {
+    fn from((name, beatmaps): (String, Vec<CollectionBeatmap>)) -> Self {
+        Self {
+            beatmaps: beatmaps.into_iter().map(OsdbBeatmap::from).collect(),
+            checksums: Vec::new(),
+            name,
+            online_id: -1,
+        }
+    }
+}
+
+/// The contents of a Collection Manager `.osdb` file.
+#[derive(Clone, Debug)]
+pub struct OsdbFile {
+    pub collections: Vec<OsdbCollection>,
+    /// The date of the file as an OLE Automation date.
+    pub date: f64,
+    /// The name of the last editor of the file.
+    pub editor: String,
+    /// The parsed compressed file, written back as is while its contents are not modified.
+    source: Option<CompressedSource>,
+    pub version: OsdbVersion,
+}
+
+impl PartialEq for OsdbFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.collections == other.collections
+            && self.date == other.date
+            && self.editor == other.editor
+            && self.version == other.version
+    }
+}
+
+/// A compressed `.osdb` file along with its decompressed contents.
+///
+/// Collection Manager and the generator compress the files with different encoders, so the
+/// original file is kept to write the unmodified files byte for byte.
+#[derive(Clone, Debug)]
+struct CompressedSource {
+    buffer: Vec<u8>,
+    decompressed: Vec<u8>,
+}
+
+impl OsdbFile {
+    /// Creates a new [`OsdbFile`] in the format written by the generator.
+    pub fn new(collections: Vec<OsdbCollection>) -> Self {
+        Self {
+            collections,
+            date: 1.0,
+            editor: String::from(CREATOR),
+            source: None,
+            version: OSDB_VERSION,
+        }
+    }
+
+    /// Returns whether the `buffer` starts with the header of a `.osdb` file.
+    pub fn is_osdb(buffer: &[u8]) -> bool {
+        buffer.get(1..5) == Some(b"o!dm".as_slice())
+    }
+
+    /// Adds the `collections` next to the existing ones.
+    ///
+    /// The beatmaps of the collections whose name is already taken replace the beatmaps of the
+    /// existing collection if `replace` is set, or are otherwise added to them, skipping the
+    /// beatmaps it already contains.
+    pub fn merge(&mut self, collections: Vec<OsdbCollection>, replace: bool) {
+        for collection in collections {
+            let Some(existing) = self
+                .collections
+                .iter_mut()
+                .find(|existing| existing.name == collection.name)
+            else {
+                self.collections.push(collection);
+                continue;
+            };
+            if replace {
+                existing.beatmaps = collection.beatmaps;
+                existing.checksums = collection.checksums;
+                continue;
+            }
+            for beatmap in collection.beatmaps {
+                if !existing
+                    .beatmaps
+                    .iter()
+                    .any(|existing| existing.checksum == beatmap.checksum)
+                {
+                    existing.beatmaps.push(beatmap);
+                }
+            }
+            for checksum in collection.checksums {
+                if !existing.checksums.contains(&checksum) {
+                    existing.checksums.push(checksum);
+                }
+            }
+        }
+    }
+
+    /// Parses the contents of a `.osdb` file, decompressing them if needed.
+    ///
+    /// Returns [`Error::InvalidCollection`] if the `buffer` is not a valid `.osdb` file of a
+    /// supported version or decompresses to more than [`MAX_DECOMPRESSED`] bytes.
+    pub async fn parse(buffer: &[u8]) -> ServerResult<Self> {
+        let mut reader = Reader::new(buffer);
+        let version = OsdbVersion::from_name(&reader.read_string()?)?;
+        if !version.is_compressed() {
+            return Self::parse_body(reader, version);
+        }
+        let mut decompressed = Vec::new();
+        GzipDecoder::new(reader.read_rest())
+            .take(MAX_DECOMPRESSED + 1)
+            .read_to_end(&mut decompressed)
+            .await
+            .map_err(|error| Error::InvalidCollection(error.to_string()))?;
+        if decompressed.len() as u64 > MAX_DECOMPRESSED {
+            return Err(Error::InvalidCollection(String::from(
+                "Decompressed .osdb file is too large",
+            )));
+        }
+        let mut reader = Reader::new(&decompressed);
+        if OsdbVersion::from_name(&reader.read_string()?)? != version {
+            return Err(Error::InvalidCollection(String::from(
+                "Mismatched .osdb versions",
+            )));
+        }
+        let mut osdb = Self::parse_body(reader, version)?;
+        osdb.source = Some(CompressedSource {
+            buffer: buffer.to_vec(),
+            decompressed,
+        });
+        Ok(osdb)
+    }
+
+    /// Parses the uncompressed contents of a `.osdb` file after its version.
+    fn parse_body(mut reader: Reader, version: OsdbVersion) -> ServerResult<Self> {
+        let date = reader.read_f64()?;
+        let editor = reader.read_string()?;
+        let collections_count = reader.read_count()?;
+        let mut collections = Vec::new();
+        for _ in 0..collections_count {
+            let name = reader.read_string()?;
+            let online_id = if version.is_compressed() {
+                reader.read_i32()?
+            } else {
+                -1
+            };
+            let beatmaps_count = reader.read_count()?;
+            let mut beatmaps = Vec::new();
+            for _ in 0..beatmaps_count {
+                let id = reader.read_i32()?;
+                let beatmapset_id = reader.read_i32()?;
+                let metadata = if version.minimal {
+                    None
+                } else {
+                    Some(OsdbBeatmapMetadata {
+                        artist: reader.read_string()?,
+                        title: reader.read_string()?,
+                        difficulty_name: reader.read_string()?,
+                    })
+                };
+                let checksum = reader.read_string()?;
+                let comment = reader.read_string()?;
+                let (mode, difficulty_rating) = if version.has_difficulty() {
+                    (reader.read_u8()?, reader.read_f64()?)
+                } else {
+                    (0, 0.0)
+                };
+                beatmaps.push(OsdbBeatmap {
+                    beatmapset_id,
+                    checksum,
+                    comment,
+                    difficulty_rating,
+                    id,
+                    metadata,
+                    mode,
+                });
+            }
+            let checksums_count = reader.read_count()?;
+            let mut checksums = Vec::new();
+            for _ in 0..checksums_count {
+                checksums.push(reader.read_string()?);
+            }
+            collections.push(OsdbCollection {
+                beatmaps,
+                checksums,
+                name,
+                online_id,
+            });
+        }
+        if reader.read_string()? != FOOTER {
+            return Err(Error::InvalidCollection(String::from(
+                "Missing .osdb footer",
+            )));
+        }
+        reader.finish()?;
+        Ok(Self {
+            collections,
+            date,
+            editor,
+            source: None,
+            version,
+        })
+    }
+
+    /// Returns the binary representation of the file in its version, compressing it if needed.
+    ///
+    /// A parsed compressed file whose contents were not modified is returned as it was parsed.
+    pub async fn to_bytes(&self) -> ServerResult<Vec<u8>> {
+        let mut buffer = Vec::<u8>::new();
+        write_string(&mut buffer, &self.version.name());
+        buffer.extend_from_slice(self.date.to_le_bytes().as_slice());
+        write_string(&mut buffer, &self.editor);
+        write_collections(&mut buffer, &self.collections, self.version);
+        write_string(&mut buffer, FOOTER);
+        if !self.version.is_compressed() {
+            return Ok(buffer);
+        }
+        if let Some(source) = self
+            .source
+            .as_ref()
+            .filter(|source| source.decompressed == buffer)
+        {
+            return Ok(source.buffer.clone());
+        }
+        let mut encoded = Vec::<u8>::new();
+        write_string(&mut encoded, &self.version.name());
+        let mut encoder = GzipEncoder::new(Vec::<u8>::new());
+        encoder.write_all(buffer.as_slice()).await?;
+        encoder.shutdown().await?;
+        encoded.extend_from_slice(encoder.into_inner().as_slice());
+        Ok(encoded)
+    }
+}
 
 /// Returns a `.osdb` buffer generated from a set of collections of
 /// beatmaps.
@@ -16,41 +373,52 @@ const CREATOR: &str = "Sombrax79";
 ///
 /// * `collections` - A set of collections of beatmaps grouped by the
 ///   collection's name.
+/// * `include_metadata` - An indicator of whether to write the full `o!dm8` format, which
+///   includes the artist, title and difficulty name of the beatmaps.
 pub async fn generate_osdb(
     collections: Vec<(String, Vec<CollectionBeatmap>)>,
+    include_metadata: bool,
 ) -> ServerResult<Vec<u8>> {
-    let mut buffer = Vec::<u8>::new();
-    write_string(&mut buffer, OSDB_VERSION);
-    buffer.extend_from_slice(1.0_f64.to_le_bytes().as_slice());
-    write_string(&mut buffer, CREATOR);
-    write_collections(&mut buffer, collections);
-    write_string(&mut buffer, FOOTER);
-    let mut encoded = Vec::<u8>::new();
-    write_string(&mut encoded, OSDB_VERSION);
-    let mut encoder = GzipEncoder::new(Vec::<u8>::new());
-    encoder.write_all(buffer.as_slice()).await?;
-    encoder.shutdown().await?;
-    encoded.extend_from_slice(encoder.into_inner().as_slice());
-    Ok(encoded)
+    let mut osdb = OsdbFile::new(collections.into_iter().map(OsdbCollection::from).collect());
+    if include_metadata {
+        osdb.version = OSDB_METADATA_VERSION;
+    } else {
+        for beatmap in osdb
+            .collections
+            .iter_mut()
+            .flat_map(|collection| &mut collection.beatmaps)
+        {
+            beatmap.mode = MINIMAL_PLAY_MODE;
+        }
+    }
+    osdb.to_bytes().await
 }
 
 /// Writes a collection of beatmaps into the `.osdb` buffer.
 ///
 /// # Arguments
 ///
-/// * `buffer` - A previously initialized `.osdb` buffer.
 /// * `beatmaps` - A collection of beatmaps.
-fn write_beatmaps(beatmaps: Vec<CollectionBeatmap>, buffer: &mut Vec<u8>) {
+/// * `buffer` - A previously initialized `.osdb` buffer.
+/// * `version` - The version of the `.osdb` buffer.
+fn write_beatmaps(beatmaps: &[OsdbBeatmap], buffer: &mut Vec<u8>, version: OsdbVersion) {
     buffer.extend_from_slice((beatmaps.len() as i32).to_le_bytes().as_slice());
     for beatmap in beatmaps {
         buffer.extend_from_slice((beatmap.id).to_le_bytes().as_slice());
         buffer.extend_from_slice((beatmap.beatmapset_id).to_le_bytes().as_slice());
+        if !version.minimal {
+            let metadata = beatmap.metadata.clone().unwrap_or_default();
+            write_string(buffer, &metadata.artist);
+            write_string(buffer, &metadata.title);
+            write_string(buffer, &metadata.difficulty_name);
+        }
         write_string(buffer, &beatmap.checksum);
-        buffer.push(0_u8);
-        buffer.push(1_u8);
-        buffer.extend_from_slice((beatmap.difficulty_rating as f64).to_le_bytes().as_slice());
+        write_string(buffer, &beatmap.comment);
+        if version.has_difficulty() {
+            buffer.push(beatmap.mode);
+            buffer.extend_from_slice(beatmap.difficulty_rating.to_le_bytes().as_slice());
+        }
     }
-    buffer.extend_from_slice(0_i32.to_le_bytes().as_slice());
 }
 
 /// Writes a set of collections of beatmaps into the `.osdb` buffer.
@@ -58,24 +426,178 @@ fn write_beatmaps(beatmaps: Vec<CollectionBeatmap>, buffer: &mut Vec<u8>) {
 /// # Arguments
 ///
 /// * `buffer` - A previously initialized `.osdb` buffer.
-/// * `collections` - A set of collections of beatmaps grouped by the
-///   collection's name.
-fn write_collections(buffer: &mut Vec<u8>, collections: Vec<(String, Vec<CollectionBeatmap>)>) {
+/// * `collections` - A set of collections of beatmaps.
+/// * `version` - The version of the `.osdb` buffer.
+fn write_collections(buffer: &mut Vec<u8>, collections: &[OsdbCollection], version: OsdbVersion) {
     buffer.extend_from_slice((collections.len() as i32).to_le_bytes().as_slice());
-    for (collection, beatmaps) in collections {
-        write_string(buffer, &collection);
-        buffer.extend_from_slice((-1_i32).to_le_bytes().as_slice());
-        write_beatmaps(beatmaps, buffer);
+    for collection in collections {
+        write_string(buffer, &collection.name);
+        if version.is_compressed() {
+            buffer.extend_from_slice(collection.online_id.to_le_bytes().as_slice());
+        }
+        write_beatmaps(&collection.beatmaps, buffer, version);
+        buffer.extend_from_slice((collection.checksums.len() as i32).to_le_bytes().as_slice());
+        for checksum in &collection.checksums {
+            write_string(buffer, checksum);
+        }
     }
 }
 
-/// Writes a string into the `.osdb` buffer.
+/// Writes a string into the `.osdb` buffer prefixed by its 7-bit encoded length.
 ///
 /// # Arguments
 ///
 /// * `buffer` - A previously initialized `.osdb` buffer.
 /// * `string` - A string.
 fn write_string(buffer: &mut Vec<u8>, string: &str) {
-    buffer.push(string.len() as u8);
+    write_string_length(buffer, string.len());
     buffer.extend_from_slice(string.as_bytes());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        OsdbBeatmap, OsdbBeatmapMetadata, OsdbCollection, OsdbFile, OsdbVersion, OSDB_VERSION,
+    };
+    use crate::Error;
+    use tokio::fs;
+
+    fn osdb_file(version: OsdbVersion) -> OsdbFile {
+        let has_difficulty = !version.minimal || version.number >= 8;
+        let mut file = OsdbFile::new(vec![OsdbCollection {
+            beatmaps: vec![OsdbBeatmap {
+                beatmapset_id: 128931,
+                checksum: String::from("e4ad76f1a6b4e3bcfb1652d49159eff9"),
+                comment: String::from("Stamina"),
+                difficulty_rating: if has_difficulty { 5.14 } else { 0.0 },
+                id: 847314,
+                metadata: (!version.minimal).then(|| OsdbBeatmapMetadata {
+                    artist: String::from("Artist"),
+                    difficulty_name: String::from("Insane"),
+                    title: String::from("Title"),
+                }),
+                mode: u8::from(has_difficulty),
+            }],
+            checksums: vec![String::from("d6c8ba1406ad3de9381f51abf74be544")],
+            name: String::from("OST"),
+            online_id: if version.number >= 7 { 12 } else { -1 },
+        }]);
+        file.date = 45000.5;
+        file.version = version;
+        file
+    }
+
+    #[tokio::test]
+    async fn test_parse_osdb() {
+        for file in ["./test_files/bpm.osdb", "./test_files/collection.osdb"] {
+            let buffer = fs::read(file).await.unwrap();
+            assert!(OsdbFile::is_osdb(&buffer));
+            let osdb = OsdbFile::parse(&buffer).await.unwrap();
+            assert_eq!(osdb.version, OSDB_VERSION);
+            assert_eq!(osdb.to_bytes().await.unwrap(), buffer);
+        }
+        let osdb = OsdbFile::parse(&fs::read("./test_files/bpm.osdb").await.unwrap())
+            .await
+            .unwrap();
+        let names: Vec<_> = osdb.collections.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["170-179", "190-199", "210-219"]);
+        assert_eq!(osdb.collections[0].beatmaps[0].id, 847314);
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_osdb_fixtures() {
+        for (file, minimal, number) in [
+            ("./test_files/odm6.osdb", false, 6),
+            ("./test_files/odm7.osdb", false, 7),
+            ("./test_files/odm7min.osdb", true, 7),
+        ] {
+            let buffer = fs::read(file).await.unwrap();
+            let osdb = OsdbFile::parse(&buffer).await.unwrap();
+            assert_eq!(osdb.version, OsdbVersion { minimal, number });
+            assert_eq!(osdb.editor, "Sombrax79");
+            let names: Vec<_> = osdb.collections.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, ["Stamina", "Taiko streams"]);
+            assert_eq!(
+                osdb.collections[0].online_id,
+                if number >= 7 { 4521 } else { -1 }
+            );
+            assert_eq!(osdb.collections[0].checksums.len(), 1);
+            let beatmap = &osdb.collections[0].beatmaps[1];
+            assert_eq!(beatmap.id, 1949106);
+            assert_eq!(beatmap.checksum, "da8aae79c8f3306b5d65ec951874a7fb");
+            assert_eq!(beatmap.comment, "burst heavy");
+            assert_eq!(
+                beatmap
+                    .metadata
+                    .as_ref()
+                    .map(|metadata| metadata.title.as_str()),
+                (!minimal).then_some("Blue Zenith")
+            );
+            let taiko = &osdb.collections[1].beatmaps[0];
+            if minimal {
+                assert_eq!((taiko.mode, taiko.difficulty_rating), (0, 0.0));
+            } else {
+                assert_eq!((taiko.mode, taiko.difficulty_rating), (1, 6.0));
+            }
+            assert_eq!(osdb.to_bytes().await.unwrap(), buffer);
+            let mut modified = osdb.clone();
+            modified.collections[0].beatmaps[1].comment = String::from("stamina");
+            let written = modified.to_bytes().await.unwrap();
+            assert_ne!(written, buffer);
+            assert_eq!(OsdbFile::parse(&written).await.unwrap(), modified);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_long_names_osdb() {
+        let buffer = fs::read("./test_files/long_names.osdb").await.unwrap();
+        let mut osdb = OsdbFile::parse(&buffer).await.unwrap();
+        assert_eq!(osdb.collections[0].name, "Stamina training ".repeat(18));
+        assert_eq!(osdb.collections[1].name, "ストリーム練習 170-179 ★");
+        assert_eq!(
+            osdb.collections[0].beatmaps[0]
+                .metadata
+                .as_ref()
+                .unwrap()
+                .artist,
+            "アーティスト ".repeat(20)
+        );
+        assert_eq!(osdb.to_bytes().await.unwrap(), buffer);
+        osdb.version = OSDB_VERSION;
+        osdb.collections[0].beatmaps[0].metadata = None;
+        let compressed = osdb.to_bytes().await.unwrap();
+        assert_eq!(OsdbFile::parse(&compressed).await.unwrap(), osdb);
+    }
+
+    #[tokio::test]
+    async fn test_parse_osdb_versions() {
+        for number in 6..=8 {
+            for minimal in [false, true] {
+                let osdb = osdb_file(OsdbVersion { minimal, number });
+                let buffer = osdb.to_bytes().await.unwrap();
+                assert_eq!(OsdbFile::parse(&buffer).await.unwrap(), osdb);
+            }
+        }
+        for name in ["o!dm6min", "o!dm7min"] {
+            assert_eq!(OsdbVersion::from_name(name).unwrap().name(), name);
+        }
+        for name in ["o!dm5", "o!dm9min", "o!dmmin", "osdb"] {
+            assert!(OsdbVersion::from_name(name).is_err());
+        }
+        let mut invalid = osdb_file(OsdbVersion {
+            minimal: false,
+            number: 6,
+        })
+        .to_bytes()
+        .await
+        .unwrap();
+        invalid.push(0);
+        assert!(matches!(
+            OsdbFile::parse(&invalid).await,
+            Err(Error::InvalidCollection(_))
+        ));
+        assert!(!OsdbFile::is_osdb(
+            &fs::read("./test_files/collection.db").await.unwrap()
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{Error, ServerResult};
+
+/// Reads the little-endian values of a collection file buffer in order.
+pub struct Reader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        Self {
+            buffer,
+            position: 0,
+        }
+    }
+
+    /// Returns an error if there is data left to read.
+    pub fn finish(&self) -> ServerResult<()> {
+        if self.position != self.buffer.len() {
+            return Err(Error::InvalidCollection(String::from(
+                "Unexpected data after the collections",
+            )));
+        }
+        Ok(())
+    }
+
+    /// Reads the next `length` bytes.
+    pub fn read_bytes(&mut self, length: usize) -> ServerResult<&'a [u8]> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|end| *end <= self.buffer.len())
+            .ok_or_else(|| Error::InvalidCollection(String::from("Unexpected end of file")))?;
+        let bytes = &self.buffer[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    /// Reads a non-negative count of collections, beatmaps or checksums.
+    pub fn read_count(&mut self) -> ServerResult<usize> {
+        usize::try_from(self.read_i32()?)
+            .map_err(|_| Error::InvalidCollection(String::from("Negative count")))
+    }
+
+    pub fn read_f64(&mut self) -> ServerResult<f64> {
+        Ok(f64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    /// Reads all the bytes left.
+    pub fn read_rest(&mut self) -> &'a [u8] {
+        let bytes = &self.buffer[self.position..];
+        self.position = self.buffer.len();
+        bytes
+    }
+
+    pub fn read_i32(&mut self) -> ServerResult<i32> {
+        Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    /// Reads an osu! string, which is either absent or prefixed by its ULEB128 encoded length.
+    pub fn read_osu_string(&mut self) -> ServerResult<String> {
+        match self.read_u8()? {
+            0 => Ok(String::new()),
+            11 => self.read_string(),
+            _ => Err(Error::InvalidCollection(String::from(
+                "Invalid string indicator",
+            ))),
+        }
+    }
+
+    /// Reads a string prefixed by its ULEB128 encoded length.
+    pub fn read_string(&mut self) -> ServerResult<String> {
+        let length = self.read_uleb128()?;
+        String::from_utf8(self.read_bytes(length)?.to_vec())
+            .map_err(|_| Error::InvalidCollection(String::from("Invalid UTF-8 string")))
+    }
+
+    pub fn read_u8(&mut self) -> ServerResult<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    /// Reads an unsigned LEB128 encoded integer.
+    fn read_uleb128(&mut self) -> ServerResult<usize> {
+        let mut value = 0_usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.read_u8()?;
+            value |= usize::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::InvalidCollection(String::from(
+            "Invalid string length",
+        )))
+    }
+}
@@ -1,6 +1,9 @@
 use argon2::password_hash;
 use axum::{
-    extract::rejection::{ExtensionRejection, TypedHeaderRejection},
+    extract::{
+        multipart::MultipartError,
+        rejection::{ExtensionRejection, TypedHeaderRejection},
+    },
     http::{header::InvalidHeaderValue, StatusCode},
     response::{IntoResponse, Response},
 };
@@ -21,6 +24,10 @@ pub enum Error {
     FileStorage(storage::Error),
     HeaderValue(InvalidHeaderValue),
     IO(io::Error),
+    InvalidCollection(String),
+    InvalidGrouping(String),
+    Json(serde_json::Error),
+    Multipart(MultipartError),
     OsuApi(osu_api::Error),
     PasswordHash(password_hash::Error),
     Redis(RedisError),
@@ -43,6 +50,14 @@ impl fmt::Display for Error {
             Self::FileStorage(storage) => storage.fmt(formatter),
             Self::HeaderValue(header_value) => header_value.fmt(formatter),
             Self::IO(io) => io.fmt(formatter),
+            Self::InvalidCollection(reason) => {
+                write!(formatter, "Invalid collection file: {reason}")
+            }
+            Self::InvalidGrouping(reason) => {
+                write!(formatter, "Invalid collection grouping: {reason}")
+            }
+            Self::Json(json) => json.fmt(formatter),
+            Self::Multipart(multipart) => multipart.fmt(formatter),
             Self::OsuApi(osu_api) => osu_api.fmt(formatter),
             Self::PasswordHash(password_hash) => password_hash.fmt(formatter),
             Self::Redis(redis) => redis.fmt(formatter),
@@ -87,6 +102,18 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<MultipartError> for Error {
+    fn from(error: MultipartError) -> Self {
+        Self::Multipart(error)
+    }
+}
+
 impl From<osu_api::Error> for Error {
     fn from(error: osu_api::Error) -> Self {
         Self::OsuApi(error)
@@ -113,11 +140,16 @@ impl From<TypedHeaderRejection> for Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let status_code = if let Self::Authorization = self {
-            StatusCode::UNAUTHORIZED
-        } else {
-            tracing::error!("Description={:?}", self);
-            StatusCode::INTERNAL_SERVER_ERROR
+        let status_code = match self {
+            Self::Authorization => StatusCode::UNAUTHORIZED,
+            Self::DynamicFilter(_)
+            | Self::InvalidCollection(_)
+            | Self::InvalidGrouping(_)
+            | Self::Multipart(_) => StatusCode::BAD_REQUEST,
+            _ => {
+                tracing::error!("Description={:?}", self);
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
         };
         (
             status_code,
@@ -134,6 +166,10 @@ impl IntoResponse for Error {
                 }
                 Self::HeaderValue(_) => "An error ocurred while setting a response header.",
                 Self::IO(_) => "An error ocurred while performing an IO operation.",
+                Self::InvalidCollection(_) => "The collection file is not valid.",
+                Self::InvalidGrouping(_) => "The grouping of the collections is not valid.",
+                Self::Json(_) => "An error ocurred while serializing a JSON value.",
+                Self::Multipart(_) => "An error ocurred while reading the uploaded files.",
                 Self::OsuApi(_) => "An error ocurred while performing an osu! API request.",
                 Self::PasswordHash(_) => {
                     "An error ocurred while performing a password hashing operation."
@@ -9,7 +9,13 @@ use axum::{
     Extension, Router, Server,
 };
 use dotenv::dotenv;
-use ost_utils::{osu_api, storage};
+use ost_utils::{
+    osu_api::{
+        self,
+        cache::{CacheTtls, Endpoint, RedisCache},
+    },
+    storage,
+};
 use redis::aio::ConnectionManager;
 use sqlx::postgres::PgPoolOptions;
 use std::{env, net::SocketAddr, time::Duration};
@@ -53,8 +59,26 @@ async fn main() -> GenericResult {
                 })),
         )
         .init();
+    let cache = ConnectionManager::new(redis::Client::open(env::var("REDIS_URL")?)?).await?;
+    let mut osu_api = osu_api::Client::from_environment_with_shared_rate_limiter(cache.clone(), 5)?;
+    if let Some(key) = env::var("OSU_API_CACHE_KEY")
+        .ok()
+        .filter(|key| !key.is_empty())
+    {
+        osu_api = osu_api.with_cache(
+            RedisCache::new(cache.clone(), key),
+            CacheTtls::default().with(Endpoint::User, Duration::from_secs(3_600)),
+        );
+    }
     let app = Router::new()
-        .nest("/api", routes::api_routes())
+        .nest(
+            "/api",
+            routes::api_routes(
+                env::var("OSU_API_REDIRECT_URI")
+                    .ok()
+                    .filter(|uri| !uri.is_empty()),
+            ),
+        )
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|request: &Request<_>| {
@@ -74,11 +98,9 @@ async fn main() -> GenericResult {
                 }),
         )
         .layer(Extension(database))
-        .layer(Extension(
-            ConnectionManager::new(redis::Client::open(env::var("REDIS_URL")?)?).await?,
-        ))
+        .layer(Extension(cache.clone()))
         .layer(Extension(storage::Client::from_environment().await?))
-        .layer(Extension(osu_api::Client::from_environment(5)?));
+        .layer(Extension(osu_api));
     let socket_address = SocketAddr::from(([0, 0, 0, 0], 8080));
     tracing::info!("Listening on {socket_address}");
     Server::bind(&socket_address)
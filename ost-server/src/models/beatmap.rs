@@ -1,11 +1,37 @@
 use super::{filter::Filter, order::Order, Limit};
-use crate::{collection_generator::CollectionBeatmap, ServerResult};
+use crate::{
+    collection_generator::{CollectionBeatmap, ListBeatmap},
+    Error, ServerResult,
+};
+use futures::{stream, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use sqlx::{
+    postgres::PgArguments,
+    query::QueryAs,
     query_as,
     types::chrono::{DateTime, Utc},
     FromRow, Pool, Postgres, Type,
 };
+use tokio::sync::mpsc;
+
+/// The number of beatmaps of an exported list retrieved ahead of the response.
+const LIST_BUFFER: usize = 64;
+
+/// The double time beatmaps joined with the metadata of their beatmaps, exposing every column the
+/// filters of a collection file can refer to.
+const DOUBLE_TIME_COLLECTION_SOURCE: &str = r#"(
+    SELECT double_time_beatmaps.accuracy, double_time_beatmaps.approach_rate, beatmaps.artist,
+        beatmaps.artist_unicode, beatmaps.beatmapset_id, double_time_beatmaps.bpm,
+        beatmaps.checksum, double_time_beatmaps.circle_size, beatmaps.creator,
+        beatmaps.difficulty_name, double_time_beatmaps.difficulty_rating,
+        beatmaps.favorite_count, double_time_beatmaps.id, double_time_beatmaps.last_updated,
+        double_time_beatmaps.length, double_time_beatmaps.longest_stream,
+        double_time_beatmaps.performance_100, double_time_beatmaps.performance_95,
+        beatmaps.play_count, double_time_beatmaps.ranked_status,
+        double_time_beatmaps.streams_density, double_time_beatmaps.streams_length,
+        double_time_beatmaps.streams_spacing, beatmaps.title, beatmaps.title_unicode
+    FROM double_time_beatmaps INNER JOIN beatmaps ON beatmaps.id = double_time_beatmaps.id
+) AS double_time_beatmaps"#;
 
 #[derive(FromRow, Serialize)]
 pub struct Beatmap {
@@ -30,9 +56,15 @@ pub struct Beatmap {
 pub struct BeatmapByPage {
     pub accuracy: f32,
     pub approach_rate: f32,
+    pub artist: String,
+    pub artist_unicode: String,
     pub beatmapset_id: i32,
     pub bpm: i16,
+    pub card_url: String,
     pub circle_size: f32,
+    pub cover_url: String,
+    pub creator: String,
+    pub creator_id: i32,
     pub difficulty_rating: f32,
     pub favorite_count: i32,
     pub id: i32,
@@ -47,6 +79,7 @@ pub struct BeatmapByPage {
     pub streams_length: i16,
     pub streams_spacing: f32,
     pub title: String,
+    pub title_unicode: String,
 }
 
 #[derive(Serialize)]
@@ -55,11 +88,34 @@ pub struct BeatmapPagination {
     pub beatmaps: Vec<BeatmapByPage>,
 }
 
+/// The modifications of the beatmaps included in a collection file.
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mods {
+    Both,
+    DoubleTime,
+    #[default]
+    NoMod,
+}
+
+impl Mods {
+    /// Returns whether to use the double time beatmaps for each retrieval, in order.
+    fn use_double_time(self) -> &'static [bool] {
+        match self {
+            Self::Both => &[false, true],
+            Self::DoubleTime => &[true],
+            Self::NoMod => &[false],
+        }
+    }
+}
+
 #[derive(Clone, Copy, Deserialize, Serialize, Type)]
 #[sqlx(type_name = "enum_ranked_status", rename_all = "snake_case")]
 #[serde(rename_all = "snake_case")]
 pub enum RankedStatus {
+    Approved,
     Loved,
+    Qualified,
     Ranked,
     Unranked,
 }
@@ -77,23 +133,116 @@ pub async fn retrieve(database: &Pool<Postgres>, id: i32) -> ServerResult<Option
     .await?)
 }
 
+/// Retrieves the beatmaps of a collection file matching the `filters` with the `mods`,
+/// followed by the double time beatmaps when both are included.
 pub async fn retrieve_filtered_collection(
     database: &Pool<Postgres>,
     filters: &[Filter],
+    mods: Mods,
     title: Option<String>,
 ) -> ServerResult<Vec<CollectionBeatmap>> {
-    let beatmaps_sql = format!(
-        r#"SELECT beatmapset_id, bpm, checksum, difficulty_rating, id, streams_length FROM beatmaps {}"#,
-        Filter::parse_multiple(filters, title.is_some())
-    );
-    let mut beatmaps = query_as::<_, CollectionBeatmap>(&beatmaps_sql);
+    let mut beatmaps = Vec::new();
+    for &use_double_time in mods.use_double_time() {
+        let beatmaps_sql = filtered_collection_sql(
+            "artist, beatmapset_id, bpm, checksum, difficulty_name, difficulty_rating, id,
+                ranked_status, streams_density, streams_length, title",
+            filters,
+            title.is_some(),
+            use_double_time,
+        );
+        let query = query_as::<_, CollectionBeatmap>(&beatmaps_sql);
+        beatmaps.extend(
+            bind_filters(query, filters, title.clone())?
+                .fetch_all(database)
+                .await?,
+        );
+    }
+    Ok(beatmaps)
+}
+
+/// Streams the beatmaps of an exported list matching the `filters` with the `mods` sorted by
+/// their IDs, followed by the double time beatmaps when both are included.
+///
+/// The `filters` are validated before the stream is returned, while the beatmaps are retrieved by
+/// a separate task as the stream is consumed.
+pub fn stream_filtered_list(
+    database: Pool<Postgres>,
+    filters: Vec<Filter>,
+    mods: Mods,
+    title: Option<String>,
+) -> ServerResult<impl Stream<Item = ServerResult<ListBeatmap>>> {
+    for filter in &filters {
+        filter.validate()?;
+    }
+    let (sender, receiver) = mpsc::channel(LIST_BUFFER);
+    tokio::spawn(async move {
+        for &use_double_time in mods.use_double_time() {
+            let beatmaps_sql = format!(
+                "{} ORDER BY id",
+                filtered_collection_sql(
+                    "beatmapset_id, bpm, difficulty_rating, id, longest_stream, streams_density,
+                        streams_length, streams_spacing, title",
+                    &filters,
+                    title.is_some(),
+                    use_double_time,
+                )
+            );
+            let query = query_as::<_, ListBeatmap>(&beatmaps_sql);
+            let query = match bind_filters(query, &filters, title.clone()) {
+                Ok(query) => query,
+                Err(error) => {
+                    let _ = sender.send(Err(error)).await;
+                    return;
+                }
+            };
+            let mut beatmaps = query.fetch(&database);
+            while let Some(beatmap) = beatmaps.next().await {
+                if sender.send(beatmap.map_err(Error::from)).await.is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    Ok(stream::unfold(receiver, |mut receiver| async move {
+        let beatmap = receiver.recv().await?;
+        Some((beatmap, receiver))
+    }))
+}
+
+/// Binds the values of the `filters` and the `title` to a query built by
+/// [`filtered_collection_sql`].
+fn bind_filters<'q, T>(
+    mut query: QueryAs<'q, Postgres, T, PgArguments>,
+    filters: &[Filter],
+    title: Option<String>,
+) -> ServerResult<QueryAs<'q, Postgres, T, PgArguments>> {
     for filter in filters {
-        beatmaps = filter.bind(beatmaps)?;
+        query = filter.bind(query)?;
     }
     if let Some(title) = title {
-        beatmaps = beatmaps.bind(title);
+        query = query.bind(title);
     }
-    Ok(beatmaps.fetch_all(database).await?)
+    Ok(query)
+}
+
+/// Returns the query selecting the `columns` of the beatmaps matching the `filters`, along with
+/// whether they are double time beatmaps, using the statistics of the double time beatmaps and
+/// the metadata of their beatmaps if `use_double_time` is set.
+fn filtered_collection_sql(
+    columns: &str,
+    filters: &[Filter],
+    include_title: bool,
+    use_double_time: bool,
+) -> String {
+    format!(
+        r#"SELECT {columns}, {use_double_time} AS double_time FROM {} {}"#,
+        if use_double_time {
+            DOUBLE_TIME_COLLECTION_SOURCE
+        } else {
+            "beatmaps"
+        },
+        Filter::parse_multiple(filters, include_title)
+    )
 }
 
 pub async fn retrieve_by_page(
@@ -105,10 +254,11 @@ pub async fn retrieve_by_page(
 ) -> ServerResult<BeatmapPagination> {
     let parsed_filters = Filter::parse_multiple(filters, title.is_some());
     let beatmaps_sql = format!(
-        r#"SELECT accuracy, approach_rate, beatmapset_id, bpm, circle_size,
-                difficulty_rating, favorite_count, id, last_updated, length,
-                longest_stream, performance_100, performance_95, play_count,
-                ranked_status, streams_density, streams_length, streams_spacing, title
+        r#"SELECT accuracy, approach_rate, artist, artist_unicode, beatmapset_id, bpm,
+                card_url, circle_size, cover_url, creator, creator_id, difficulty_rating,
+                favorite_count, id, last_updated, length, longest_stream, performance_100,
+                performance_95, play_count, ranked_status, streams_density, streams_length,
+                streams_spacing, title, title_unicode
             FROM beatmaps {parsed_filters} {}
             LIMIT 12 OFFSET ${}"#,
         Order::parse(order),
@@ -20,14 +20,9 @@ impl Filter {
         &self,
         query: QueryAs<'q, Postgres, T, PgArguments>,
     ) -> ServerResult<QueryAs<'q, Postgres, T, PgArguments>> {
+        self.validate()?;
         Ok(match &self.value {
-            Value::Date(date) => {
-                if !matches!(self.property, Property::LastUpdated) {
-                    return Err(Error::DynamicFilter(self.property));
-                } else {
-                    query.bind(*date)
-                }
-            }
+            Value::Date(date) => query.bind(*date),
             Value::Integer(integer) => match self.property {
                 Property::Accuracy
                 | Property::ApproachRate
@@ -35,9 +30,6 @@ impl Filter {
                 | Property::DifficultyRating
                 | Property::StreamsDensity
                 | Property::StreamsSpacing => query.bind(*integer as f32),
-                Property::RankedStatus | Property::LastUpdated | Property::Id => {
-                    return Err(Error::DynamicFilter(self.property))
-                }
                 _ => query.bind(*integer),
             },
             Value::Decimal(decimal) => match self.property {
@@ -47,25 +39,10 @@ impl Filter {
                 | Property::DifficultyRating
                 | Property::StreamsDensity
                 | Property::StreamsSpacing => query.bind(*decimal),
-                Property::RankedStatus | Property::LastUpdated | Property::Id => {
-                    return Err(Error::DynamicFilter(self.property))
-                }
                 _ => query.bind(decimal.round() as i16),
             },
-            Value::RankedStatus(ranked_status) => {
-                if !matches!(self.property, Property::RankedStatus) {
-                    return Err(Error::DynamicFilter(self.property));
-                } else {
-                    query.bind(*ranked_status)
-                }
-            }
-            Value::SkippedIds(ids) => {
-                if !matches!(self.property, Property::Id) {
-                    return Err(Error::DynamicFilter(self.property));
-                } else {
-                    query.bind(ids.clone())
-                }
-            }
+            Value::RankedStatus(ranked_status) => query.bind(*ranked_status),
+            Value::SkippedIds(ids) => query.bind(ids.clone()),
         })
     }
 
@@ -76,7 +53,8 @@ impl Filter {
         }
         if include_title {
             parsed_filters.push(format!(
-                "title ILIKE CONCAT('%', ${}, '%')",
+                "CONCAT_WS(' ', title, title_unicode, artist, artist_unicode, creator)
+                    ILIKE CONCAT('%', ${}, '%')",
                 filters.len() + 1
             ))
         }
@@ -119,6 +97,24 @@ impl Filter {
             format!("{property} {operator} ${index}")
         }
     }
+
+    /// Checks that the type of the value matches the property, so that the filter can be bound.
+    pub fn validate(&self) -> ServerResult<()> {
+        let is_valid = match self.value {
+            Value::Date(_) => matches!(self.property, Property::LastUpdated),
+            Value::Integer(_) | Value::Decimal(_) => !matches!(
+                self.property,
+                Property::RankedStatus | Property::LastUpdated | Property::Id
+            ),
+            Value::RankedStatus(_) => matches!(self.property, Property::RankedStatus),
+            Value::SkippedIds(_) => matches!(self.property, Property::Id),
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(Error::DynamicFilter(self.property))
+        }
+    }
 }
 
 #[derive(Clone, Copy, Deserialize)]
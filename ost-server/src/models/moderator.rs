@@ -10,6 +10,8 @@ use crate::ServerResult;
 #[derive(Deserialize, FromRow, Serialize)]
 pub struct Moderator {
     pub id: i32,
+    /// The id of the osu! user allowed to log in as this moderator, if any.
+    pub osu_id: Option<i32>,
     pub password: String,
     pub username: String,
 }
@@ -62,6 +64,18 @@ pub async fn retrieve_from_credentials(
     Ok(None)
 }
 
+pub async fn retrieve_from_osu_id(
+    database: &Pool<Postgres>,
+    osu_id: i32,
+) -> ServerResult<Option<Moderator>> {
+    Ok(
+        query_as::<_, Moderator>(r#"SELECT * FROM moderators WHERE osu_id = $1"#)
+            .bind(osu_id)
+            .fetch_optional(database)
+            .await?,
+    )
+}
+
 pub async fn update_username(
     database: &Pool<Postgres>,
     id: i32,
@@ -89,3 +103,17 @@ pub async fn update_password(
         .rows_affected();
     Ok(rows > 0)
 }
+
+pub async fn update_osu_id(
+    database: &Pool<Postgres>,
+    id: i32,
+    osu_id: Option<i32>,
+) -> ServerResult<bool> {
+    let rows = query(r#"UPDATE moderators SET osu_id = $1 WHERE id = $2"#)
+        .bind(osu_id)
+        .bind(id)
+        .execute(database)
+        .await?
+        .rows_affected();
+    Ok(rows > 0)
+}
@@ -1,5 +1,5 @@
 use serde::Serialize;
-use sqlx::{query, query_as, FromRow, Pool, Postgres};
+use sqlx::{query, query_as, FromRow, Pool, Postgres, Transaction};
 
 use crate::ServerResult;
 
@@ -10,6 +10,8 @@ pub struct User {
     pub username: String,
 }
 
+/// Creates the user with the specified `id` or updates its language and username, renaming any
+/// other user with the same username like [`rename`].
 pub async fn create_or_update(
     database: &Pool<Postgres>,
     id: i32,
@@ -17,10 +19,7 @@ pub async fn create_or_update(
     username: String,
 ) -> ServerResult<()> {
     let mut transaction = database.begin().await?;
-    query(r#"DELETE FROM users WHERE username = $1"#)
-        .bind(&username)
-        .fetch_optional(&mut transaction)
-        .await?;
+    release_username(&mut transaction, id, &username).await?;
     query(
         r#"INSERT INTO users (id, language, username) VALUES ($1, $2, $3)
                 ON CONFLICT (id) DO
@@ -29,7 +28,7 @@ pub async fn create_or_update(
     .bind(id)
     .bind(language)
     .bind(username)
-    .execute(database)
+    .execute(&mut transaction)
     .await?;
     transaction.commit().await?;
     Ok(())
@@ -44,6 +43,38 @@ pub fn parse_country_code(country_code: String) -> String {
     })
 }
 
+/// Renames any user other than the one with the specified `id` that has the `username` to the
+/// `#<id>` placeholder, keeping its data until its own username is synchronized.
+async fn release_username(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: i32,
+    username: &str,
+) -> ServerResult<()> {
+    query(r#"UPDATE users SET username = CONCAT('#', id) WHERE username = $1 AND id != $2"#)
+        .bind(username)
+        .bind(id)
+        .execute(transaction)
+        .await?;
+    Ok(())
+}
+
+/// Updates the username of the user with the specified `id`, returning the user if it exists.
+pub async fn rename(
+    database: &Pool<Postgres>,
+    id: i32,
+    username: String,
+) -> ServerResult<Option<User>> {
+    let mut transaction = database.begin().await?;
+    release_username(&mut transaction, id, &username).await?;
+    let user = query_as::<_, User>(r#"UPDATE users SET username = $1 WHERE id = $2 RETURNING *"#)
+        .bind(username)
+        .bind(id)
+        .fetch_optional(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(user)
+}
+
 pub async fn retrieve(database: &Pool<Postgres>, username: String) -> ServerResult<Option<User>> {
     Ok(
         query_as::<_, User>(r#"SELECT * FROM users WHERE username = $1"#)
@@ -52,3 +83,72 @@ pub async fn retrieve(database: &Pool<Postgres>, username: String) -> ServerResu
             .await?,
     )
 }
+
+pub async fn retrieve_from_id(database: &Pool<Postgres>, id: i32) -> ServerResult<Option<User>> {
+    Ok(query_as::<_, User>(r#"SELECT * FROM users WHERE id = $1"#)
+        .bind(id)
+        .fetch_optional(database)
+        .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::user::{create_or_update, rename, retrieve_from_id};
+    use dotenv::dotenv;
+    use sqlx::{postgres::PgPoolOptions, query};
+    use std::{env, error::Error};
+
+    const FIRST_ID: i32 = 2_000_000_001;
+    const SECOND_ID: i32 = 2_000_000_002;
+
+    #[tokio::test]
+    #[ignore = "requires a database"]
+    async fn test_username_swap() -> Result<(), Box<dyn Error>> {
+        dotenv().ok();
+        let database = PgPoolOptions::new()
+            .connect(&env::var("DATABASE_URL")?)
+            .await?;
+        create_or_update(
+            &database,
+            FIRST_ID,
+            String::from("es"),
+            String::from("swap_a"),
+        )
+        .await?;
+        create_or_update(
+            &database,
+            SECOND_ID,
+            String::from("br"),
+            String::from("swap_b"),
+        )
+        .await?;
+        let first = rename(&database, FIRST_ID, String::from("swap_b")).await?;
+        assert_eq!(first.unwrap().username, "swap_b");
+        let second = retrieve_from_id(&database, SECOND_ID).await?.unwrap();
+        assert_eq!(second.username, format!("#{SECOND_ID}"));
+        assert_eq!(second.language, "br");
+        create_or_update(
+            &database,
+            SECOND_ID,
+            String::from("br"),
+            String::from("swap_a"),
+        )
+        .await?;
+        let first = retrieve_from_id(&database, FIRST_ID).await?.unwrap();
+        let second = retrieve_from_id(&database, SECOND_ID).await?.unwrap();
+        assert_eq!(
+            (first.username.as_str(), first.language.as_str()),
+            ("swap_b", "es")
+        );
+        assert_eq!(
+            (second.username.as_str(), second.language.as_str()),
+            ("swap_a", "br")
+        );
+        query(r#"DELETE FROM users WHERE id IN ($1, $2)"#)
+            .bind(FIRST_ID)
+            .bind(SECOND_ID)
+            .execute(&database)
+            .await?;
+        Ok(())
+    }
+}
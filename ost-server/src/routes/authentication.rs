@@ -1,21 +1,40 @@
-use crate::{models::moderator, Error, ServerResult};
+use crate::{
+    models::{moderator, user},
+    Error, ServerResult,
+};
 use axum::{
     async_trait,
-    extract::FromRequestParts,
+    extract::{FromRequestParts, Query},
     headers::Cookie,
     http::{header::SET_COOKIE, request::Parts, HeaderMap, HeaderValue, StatusCode},
-    response::IntoResponse,
-    routing::{delete, post},
+    response::{IntoResponse, Redirect},
+    routing::{delete, get, post},
     Extension, Json, Router, TypedHeader,
 };
-use base64::engine::{general_purpose::STANDARD, Engine as _};
+use base64::engine::{
+    general_purpose::{STANDARD, URL_SAFE_NO_PAD},
+    Engine as _,
+};
+use ost_utils::osu_api;
 use rand_core::{OsRng, RngCore};
 use redis::{aio::ConnectionManager, AsyncCommands};
 use serde::Deserialize;
 use sqlx::{Pool, Postgres};
 
+const OAUTH_STATE_COOKIE: &str = "ost-oauth-state";
+const OAUTH_STATE_EXPIRATION: usize = 600;
 const SESSION_COOKIE: &str = "ost-session";
 const SESSION_CACHE_EXPIRATION: usize = 1_209_600;
+const SESSION_LENGTH: usize = 64;
+const SESSION_PREFIX: &str = "moderator-session:";
+const USER_SESSION_COOKIE: &str = "ost-user-session";
+const USER_SESSION_PREFIX: &str = "user-session:";
+
+#[derive(Deserialize)]
+pub struct AuthorizationCallback {
+    pub code: Option<String>,
+    pub state: String,
+}
 
 #[derive(Deserialize)]
 pub struct AuthenticationCredentials {
@@ -36,20 +55,34 @@ where
     type Rejection = Error;
 
     async fn from_request_parts(request: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let Extension(mut cache) =
-            Extension::<ConnectionManager>::from_request_parts(request, state).await?;
-        let cookies = TypedHeader::<Cookie>::from_request_parts(request, state)
-            .await
-            .map_err(|_| Error::Authorization)?;
-        let cookie = cookies.get(SESSION_COOKIE).ok_or(Error::Authorization)?;
-        if let Ok(id) = cache.get::<String, i32>(String::from(cookie)).await {
-            return Ok(Session {
-                id,
-                cookie: String::from(cookie),
-            });
-        }
+        retrieve_session(request, SESSION_COOKIE, SESSION_PREFIX, state).await
+    }
+}
+
+/// The redirect URI registered for the osu! OAuth application, pointing to [`osu_callback`].
+#[derive(Clone)]
+struct OsuRedirectUri(String);
+
+/// The session of a player logged in with their osu! account, where `id` is their osu! user id.
+pub struct UserSession {
+    pub id: i32,
+    pub cookie: String,
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for UserSession
+where
+    S: Send + Sync,
+{
+    type Rejection = Error;
 
-        Err(Error::Authorization)
+    async fn from_request_parts(request: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let session =
+            retrieve_session(request, USER_SESSION_COOKIE, USER_SESSION_PREFIX, state).await?;
+        Ok(UserSession {
+            id: session.id,
+            cookie: session.cookie,
+        })
     }
 }
 
@@ -58,15 +91,59 @@ async fn create_session(
     headers: &mut HeaderMap,
     id: i32,
 ) -> ServerResult<()> {
-    let mut session_bytes = Vec::from([0u8; 64]);
+    create_prefixed_session(cache, SESSION_COOKIE, headers, id, SESSION_PREFIX).await
+}
+
+/// Creates a session stored in the cache under the `prefix`, so that the sessions of each cookie
+/// can't be used in place of the others.
+async fn create_prefixed_session(
+    cache: &mut ConnectionManager,
+    cookie_name: &str,
+    headers: &mut HeaderMap,
+    id: i32,
+    prefix: &str,
+) -> ServerResult<()> {
+    let mut session_bytes = [0u8; SESSION_LENGTH];
     OsRng.fill_bytes(&mut session_bytes);
     let session = STANDARD.encode(session_bytes);
-    let cookie = format!("{SESSION_COOKIE}={session}; SameSite=Strict; Path=/");
-    cache.set_ex(session, id, SESSION_CACHE_EXPIRATION).await?;
-    headers.insert(SET_COOKIE, HeaderValue::from_str(&cookie)?);
+    let cookie = format!("{cookie_name}={session}; SameSite=Strict; Path=/");
+    cache
+        .set_ex::<_, _, ()>(format!("{prefix}{session}"), id, SESSION_CACHE_EXPIRATION)
+        .await?;
+    headers.append(SET_COOKIE, HeaderValue::from_str(&cookie)?);
     Ok(())
 }
 
+async fn retrieve_session<S: Send + Sync>(
+    request: &mut Parts,
+    cookie_name: &str,
+    prefix: &str,
+    state: &S,
+) -> ServerResult<Session> {
+    let cookies = TypedHeader::<Cookie>::from_request_parts(request, state)
+        .await
+        .map_err(|_| Error::Authorization)?;
+    let key = cookies
+        .get(cookie_name)
+        .and_then(|cookie| session_key(cookie, prefix))
+        .ok_or(Error::Authorization)?;
+    let Extension(mut cache) =
+        Extension::<ConnectionManager>::from_request_parts(request, state).await?;
+    if let Ok(id) = cache.get::<_, i32>(&key).await {
+        return Ok(Session { id, cookie: key });
+    }
+    Err(Error::Authorization)
+}
+
+/// Returns the cache key of the session stored in the `cookie`, rejecting any value that isn't a
+/// session token created by [`create_prefixed_session`].
+fn session_key(cookie: &str, prefix: &str) -> Option<String> {
+    match STANDARD.decode(cookie) {
+        Ok(session) if session.len() == SESSION_LENGTH => Some(format!("{prefix}{cookie}")),
+        _ => None,
+    }
+}
+
 async fn login(
     Extension(mut cache): Extension<ConnectionManager>,
     Extension(database): Extension<Pool<Postgres>>,
@@ -82,11 +159,107 @@ async fn login(
     }
     Ok(StatusCode::UNAUTHORIZED.into_response())
 }
+
+async fn osu_callback(
+    Extension(mut cache): Extension<ConnectionManager>,
+    Extension(database): Extension<Pool<Postgres>>,
+    Extension(osu_api): Extension<osu_api::Client>,
+    Extension(OsuRedirectUri(redirect_uri)): Extension<OsuRedirectUri>,
+    cookies: Option<TypedHeader<Cookie>>,
+    Query(callback): Query<AuthorizationCallback>,
+) -> ServerResult<impl IntoResponse> {
+    let state = cookies
+        .as_ref()
+        .and_then(|cookies| cookies.get(OAUTH_STATE_COOKIE));
+    let (Some(code), Some(state)) = (callback.code, state) else {
+        return Err(Error::Authorization);
+    };
+    if state != callback.state {
+        return Err(Error::Authorization);
+    }
+    let token = match osu_api.authorize(&code, &redirect_uri).await {
+        Ok(token) => token,
+        Err(osu_api::Error::Status(400) | osu_api::Error::Unauthorized) => {
+            return Err(Error::Authorization)
+        }
+        Err(error) => return Err(error.into()),
+    };
+    let osu_user = osu_api.retrieve_authorized_user(&token).await?;
+    let username = osu_user.username.replace(' ', "_");
+    if user::rename(&database, osu_user.id, username.clone())
+        .await?
+        .is_none()
+    {
+        user::create_or_update(
+            &database,
+            osu_user.id,
+            user::parse_country_code(osu_user.country_code),
+            username,
+        )
+        .await?;
+    }
+    let mut headers = HeaderMap::new();
+    headers.append(
+        SET_COOKIE,
+        HeaderValue::from_str(&format!("{OAUTH_STATE_COOKIE}=; Max-Age=0; Path=/"))?,
+    );
+    create_prefixed_session(
+        &mut cache,
+        USER_SESSION_COOKIE,
+        &mut headers,
+        osu_user.id,
+        USER_SESSION_PREFIX,
+    )
+    .await?;
+    if let Some(moderator) = moderator::retrieve_from_osu_id(&database, osu_user.id).await? {
+        create_session(&mut cache, &mut headers, moderator.id).await?;
+    }
+    Ok((headers, Redirect::to("/")))
+}
+
+async fn osu_login(
+    Extension(osu_api): Extension<osu_api::Client>,
+    Extension(OsuRedirectUri(redirect_uri)): Extension<OsuRedirectUri>,
+) -> ServerResult<impl IntoResponse> {
+    let mut state_bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut state_bytes);
+    let state = URL_SAFE_NO_PAD.encode(state_bytes);
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        SET_COOKIE,
+        HeaderValue::from_str(&format!(
+            "{OAUTH_STATE_COOKIE}={state}; SameSite=Lax; Path=/; HttpOnly; Max-Age={OAUTH_STATE_EXPIRATION}"
+        ))?,
+    );
+    Ok((
+        headers,
+        Redirect::to(&osu_api.authorization_url(&redirect_uri, &state)?),
+    ))
+}
+
+async fn osu_logout(
+    Extension(mut cache): Extension<ConnectionManager>,
+    session: UserSession,
+) -> ServerResult<impl IntoResponse> {
+    cache.del::<_, ()>(session.cookie).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn osu_user(
+    Extension(database): Extension<Pool<Postgres>>,
+    session: UserSession,
+) -> ServerResult<impl IntoResponse> {
+    Ok(match user::retrieve_from_id(&database, session.id).await? {
+        Some(user) => Json(user).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    })
+}
+
 async fn logout(
     Extension(mut cache): Extension<ConnectionManager>,
     session: Session,
 ) -> ServerResult<impl IntoResponse> {
-    cache.del(session.cookie).await?;
+    cache.del::<_, ()>(session.cookie).await?;
     Ok(StatusCode::NO_CONTENT)
 }
 
@@ -94,15 +267,75 @@ async fn refresh(
     Extension(mut cache): Extension<ConnectionManager>,
     session: Session,
 ) -> ServerResult<impl IntoResponse> {
-    cache.del(session.cookie).await?;
+    cache.del::<_, ()>(session.cookie).await?;
     let mut headers = HeaderMap::new();
     create_session(&mut cache, &mut headers, session.id).await?;
     Ok((StatusCode::NO_CONTENT, headers))
 }
 
-pub fn authentication_routes() -> Router {
-    Router::new()
+/// Returns the authentication routes, including the osu! login routes only if the
+/// `osu_redirect_uri` of the osu! OAuth application is configured.
+pub fn authentication_routes(osu_redirect_uri: Option<String>) -> Router {
+    let routes = Router::new()
         .route("/login", post(login))
         .route("/logout", delete(logout))
-        .route("/refresh", post(refresh))
+        .route("/refresh", post(refresh));
+    let Some(osu_redirect_uri) = osu_redirect_uri else {
+        return routes;
+    };
+    routes.merge(
+        Router::new()
+            .route("/osu", get(osu_login))
+            .route("/osu/callback", get(osu_callback))
+            .route("/osu/logout", delete(osu_logout))
+            .route("/osu/user", get(osu_user))
+            .layer(Extension(OsuRedirectUri(osu_redirect_uri))),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        routes::authentication::{
+            session_key, Session, SESSION_LENGTH, SESSION_PREFIX, USER_SESSION_PREFIX,
+        },
+        Error,
+    };
+    use axum::{extract::FromRequestParts, http::Request};
+    use base64::engine::{general_purpose::STANDARD, Engine as _};
+
+    #[test]
+    fn test_session_key() {
+        let session = STANDARD.encode([7u8; SESSION_LENGTH]);
+        assert_eq!(
+            session_key(&session, SESSION_PREFIX),
+            Some(format!("{SESSION_PREFIX}{session}"))
+        );
+        assert_eq!(
+            session_key(&STANDARD.encode([7u8; 32]), SESSION_PREFIX),
+            None
+        );
+        assert_eq!(session_key("1", SESSION_PREFIX), None);
+        assert_eq!(
+            session_key(&format!("{USER_SESSION_PREFIX}{session}"), SESSION_PREFIX),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_user_session_refused_by_session() {
+        let session = STANDARD.encode([7u8; SESSION_LENGTH]);
+        let (mut parts, _) = Request::builder()
+            .header(
+                "cookie",
+                format!("ost-session={USER_SESSION_PREFIX}{session}"),
+            )
+            .body(())
+            .unwrap()
+            .into_parts();
+        assert!(matches!(
+            Session::from_request_parts(&mut parts, &()).await,
+            Err(Error::Authorization)
+        ));
+    }
 }
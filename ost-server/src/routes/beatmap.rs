@@ -1,15 +1,32 @@
 use axum::{
-    extract::Path, http::StatusCode, response::IntoResponse, routing::post, Extension, Json, Router,
+    body::StreamBody,
+    extract::{DefaultBodyLimit, Multipart, Path},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Extension, Json, Router,
 };
+use futures::TryStreamExt;
 use serde::Deserialize;
 use sqlx::{Pool, Postgres};
 
 use crate::{
-    collection_generator::generate_collection_file,
-    models::{beatmap, filter::Filter, order::Order},
-    ServerResult,
+    collection_generator::{
+        generate_collection_file, generate_list, validate_groupings, CollectionFile, Grouping,
+        ListFormat,
+    },
+    models::{
+        beatmap::{self, Mods},
+        filter::Filter,
+        order::Order,
+    },
+    Error, ServerResult,
 };
 
+/// The largest body of a collection merge request, as `collection.db` files of large collections
+/// exceed the default limit of 2 MB.
+const MERGE_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
 #[derive(Deserialize)]
 pub struct BeatmapsByPageFilters {
     pub filters: Vec<Filter>,
@@ -20,9 +37,80 @@ pub struct BeatmapsByPageFilters {
 #[derive(Deserialize)]
 pub struct BeatmapsCollectionFilters {
     pub filters: Vec<Filter>,
+    /// The nested criteria to divide the beatmaps into collections, or none for a single
+    /// collection.
+    #[serde(default)]
+    pub groupings: Vec<Grouping>,
+    /// The format of a list of the beatmaps to export instead of a collection file.
+    #[serde(default)]
+    pub list_format: Option<ListFormat>,
+    /// Whether to include the beatmaps without modifications, with double time, or both.
+    #[serde(default)]
+    pub mods: Mods,
     pub title: Option<String>,
-    pub use_bpm_division: bool,
     pub use_osdb_format: bool,
+    /// Whether to include the artist, title and difficulty name of the beatmaps in the `.osdb`
+    /// file, which lets Collection Manager display beatmaps that are not installed.
+    #[serde(default)]
+    pub use_osdb_metadata: bool,
+}
+
+#[derive(Deserialize)]
+pub struct BeatmapsCollectionMerge {
+    pub filters: Vec<Filter>,
+    #[serde(default)]
+    pub groupings: Vec<Grouping>,
+    #[serde(default)]
+    pub mods: Mods,
+    pub replace: bool,
+    pub title: Option<String>,
+}
+
+/// Merges the generated collections into an uploaded `.osdb` or `collection.db` file.
+///
+/// The multipart body contains the `collection` file and the JSON `options` of the merge, which are
+/// validated before retrieving the beatmaps.
+async fn merge_collection(
+    Extension(database): Extension<Pool<Postgres>>,
+    mut multipart: Multipart,
+) -> ServerResult<impl IntoResponse> {
+    let mut collection = None;
+    let mut options = None;
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("collection") => collection = Some(field.bytes().await?.to_vec()),
+            Some("options") => {
+                options = Some(
+                    serde_json::from_slice::<BeatmapsCollectionMerge>(&field.bytes().await?)
+                        .map_err(|error| Error::InvalidCollection(error.to_string()))?,
+                )
+            }
+            _ => {}
+        }
+    }
+    let (Some(collection), Some(options)) = (collection, options) else {
+        return Err(Error::InvalidCollection(String::from(
+            "Missing collection file or merge options",
+        )));
+    };
+    let collection = CollectionFile::parse(&collection).await?;
+    validate_groupings(&options.groupings)?;
+    Ok((
+        StatusCode::OK,
+        collection
+            .merge(
+                beatmap::retrieve_filtered_collection(
+                    &database,
+                    &options.filters,
+                    options.mods,
+                    options.title,
+                )
+                .await?,
+                &options.groupings,
+                options.replace,
+            )
+            .await?,
+    ))
 }
 
 async fn retrieve_by_page(
@@ -45,24 +133,49 @@ async fn retrieve_by_page(
     ))
 }
 
+/// Generates a collection file, or streams a list of the beatmaps if a list format is selected.
 async fn retrieve_collection_file(
     Extension(database): Extension<Pool<Postgres>>,
     Json(payload): Json<BeatmapsCollectionFilters>,
-) -> ServerResult<impl IntoResponse> {
+) -> ServerResult<Response> {
+    if let Some(list_format) = payload.list_format {
+        let beatmaps =
+            beatmap::stream_filtered_list(database, payload.filters, payload.mods, payload.title)?;
+        let list = generate_list(beatmaps, list_format)
+            .inspect_err(|error| tracing::error!("Description={:?}", error));
+        return Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, list_format.content_type())],
+            StreamBody::new(list),
+        )
+            .into_response());
+    }
+    validate_groupings(&payload.groupings)?;
     Ok((
         StatusCode::OK,
         generate_collection_file(
-            beatmap::retrieve_filtered_collection(&database, &payload.filters, payload.title)
-                .await?,
-            payload.use_bpm_division,
+            beatmap::retrieve_filtered_collection(
+                &database,
+                &payload.filters,
+                payload.mods,
+                payload.title,
+            )
+            .await?,
+            &payload.groupings,
             payload.use_osdb_format,
+            payload.use_osdb_metadata,
         )
         .await?,
-    ))
+    )
+        .into_response())
 }
 
 pub fn beatmap_routes() -> Router {
     Router::new()
         .route("/collection", post(retrieve_collection_file))
+        .route(
+            "/collection/merge",
+            post(merge_collection).layer(DefaultBodyLimit::max(MERGE_BODY_LIMIT)),
+        )
         .route("/page/:page", post(retrieve_by_page))
 }
@@ -19,7 +19,7 @@ use axum::{
 use chrono::{Datelike, Utc};
 use ost_utils::{beatmaps_processor::process_beatmap, osu_api, storage};
 use redis::{aio::ConnectionManager, AsyncCommands};
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use sqlx::{Pool, Postgres};
 
 const LANGUAGE_CACHE_EXPIRATION: usize = 14_400;
@@ -43,17 +43,6 @@ async fn request_beatmap(
     }
 }
 
-#[derive(Serialize)]
-pub struct SimpleBeatmap {
-    bpm: i16,
-    longest_stream: i16,
-    performance_100: i16,
-    performance_95: i16,
-    streams_density: f32,
-    streams_length: i16,
-    streams_spacing: f32,
-}
-
 async fn retrieve_beatmap(
     Extension(database): Extension<Pool<Postgres>>,
     Extension(storage): Extension<storage::Client>,
@@ -61,7 +50,7 @@ async fn retrieve_beatmap(
 ) -> ServerResult<impl IntoResponse> {
     if let Some(beatmap) = beatmap::retrieve(&database, id).await? {
         Ok(Json(beatmap).into_response())
-    } else if let Ok(file) = storage.retrieve(format!("beatmaps/{id}.osu")).await {
+    } else if let Ok(file) = storage.retrieve_beatmap(None, id).await {
         let beatmap = process_beatmap(&file).await?;
         let beatmap_file =
             String::from_utf8(file).map_err(|_| storage::Error::GetObject(String::new()))?;
@@ -118,17 +107,22 @@ async fn retrieve_language(
         Ok(language)
     } else if let Some(user) = user::retrieve(&database, username.clone()).await? {
         cache
-            .set_ex(username, user.language.clone(), LANGUAGE_CACHE_EXPIRATION)
+            .set_ex::<_, _, ()>(username, user.language.clone(), LANGUAGE_CACHE_EXPIRATION)
             .await?;
         Ok(user.language)
     } else {
-        let language = if let Some(user) = osu_api.retrieve_user(username.clone(), true).await? {
-            parse_country_code(user.country_code)
+        let language = if let Some(osu_user) = osu_api.retrieve_user(username.clone(), true).await?
+        {
+            if let Some(user) = user::rename(&database, osu_user.id, username.clone()).await? {
+                user.language
+            } else {
+                parse_country_code(osu_user.country_code)
+            }
         } else {
             String::from("en")
         };
         cache
-            .set_ex(username, language.clone(), LANGUAGE_CACHE_EXPIRATION)
+            .set_ex::<_, _, ()>(username, language.clone(), LANGUAGE_CACHE_EXPIRATION)
             .await?;
         Ok(language)
     }
@@ -146,7 +140,7 @@ async fn track_activity(
         add_unique_user = user_date_id < date_id;
     }
     cache
-        .set_ex(tracking_username, date_id, TRACKING_CACHE_EXPIRATION)
+        .set_ex::<_, _, ()>(tracking_username, date_id, TRACKING_CACHE_EXPIRATION)
         .await?;
     record_activity(add_unique_user, database, date_id).await?;
     Ok(())
@@ -178,7 +172,7 @@ async fn update_language(
         )
         .await?;
         cache
-            .set_ex(new_username, payload.language, LANGUAGE_CACHE_EXPIRATION)
+            .set_ex::<_, _, ()>(new_username, payload.language, LANGUAGE_CACHE_EXPIRATION)
             .await?;
         Ok(StatusCode::NO_CONTENT)
     } else {
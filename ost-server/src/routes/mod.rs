@@ -11,9 +11,11 @@ use bot::bot_routes;
 use moderator::moderator_routes;
 use submission::submission_routes;
 
-pub fn api_routes() -> Router {
+/// Returns the API routes, where the osu! login routes are only included if the
+/// `osu_redirect_uri` of the osu! OAuth application is configured.
+pub fn api_routes(osu_redirect_uri: Option<String>) -> Router {
     Router::new()
-        .nest("/authentication", authentication_routes())
+        .nest("/authentication", authentication_routes(osu_redirect_uri))
         .nest("/beatmap", beatmap_routes())
         .nest("/bot", bot_routes())
         .nest("/moderator", moderator_routes())
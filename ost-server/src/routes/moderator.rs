@@ -4,7 +4,7 @@ use sqlx::{Pool, Postgres};
 
 use crate::{models::moderator, ServerResult};
 
-use super::authentication::Session;
+use super::authentication::{Session, UserSession};
 
 #[derive(Deserialize)]
 struct Moderator {
@@ -26,6 +26,31 @@ struct Credentials {
     credentials: String,
 }
 
+async fn delete_osu_id(
+    Extension(database): Extension<Pool<Postgres>>,
+    session: Session,
+) -> ServerResult<impl IntoResponse> {
+    if moderator::update_osu_id(&database, session.id, None).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+/// Links the moderator to the osu! account the user is logged in with, so that the osu! ID is
+/// never taken from the request.
+async fn update_osu_id(
+    Extension(database): Extension<Pool<Postgres>>,
+    session: Session,
+    user_session: UserSession,
+) -> ServerResult<impl IntoResponse> {
+    if moderator::update_osu_id(&database, session.id, Some(user_session.id)).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
 async fn update_password(
     Extension(database): Extension<Pool<Postgres>>,
     session: Session,
@@ -53,6 +78,7 @@ async fn update_username(
 pub fn moderator_routes() -> Router {
     Router::new()
         .route("/", post(create))
+        .route("/osu_id", post(update_osu_id).delete(delete_osu_id))
         .route("/password", post(update_password))
         .route("/username", post(update_username))
 }
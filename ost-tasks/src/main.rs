@@ -1,3 +1,4 @@
+use ost_utils::storage::models::RANKED_CURSOR;
 use std::{
     env,
     error::Error,
@@ -5,7 +6,11 @@ use std::{
     time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tasks::{
-    periodic::{add_beatmaps, check_submissions, process_submissions, synchronize_with_osu_api},
+    maintenance::{check_star_ratings, recompress_files, reconcile_files, reset_cursor},
+    periodic::{
+        add_beatmaps, check_submissions, process_submissions, synchronize_users,
+        synchronize_with_osu_api,
+    },
     setup::{setup_files, update_collection},
     Services,
 };
@@ -18,6 +23,9 @@ mod models;
 mod osu_files;
 mod tasks;
 
+/// The number of beatmaps sampled when checking the star ratings.
+const STAR_RATING_SAMPLE: i64 = 1_000;
+
 pub type TaskResult<T> = Result<T, Box<dyn Error + Sync + Send>>;
 
 #[tokio::main]
@@ -43,9 +51,17 @@ async fn main() -> TaskResult<()> {
             let current_time = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
             let services = Services::setup().await?;
             synchronize_with_osu_api(services.clone()).await?;
+            synchronize_users(services.clone()).await?;
             add_beatmaps(current_time, services.clone()).await?;
             check_submissions(services.clone()).await?;
-            process_submissions(services).await?;
+            process_submissions(services.clone()).await?;
+            let metrics = services.osu_api.rate_limiter_metrics();
+            tracing::info!(
+                "osu! API requests: {}, throttled: {}, waited: {}s",
+                metrics.requests,
+                metrics.throttled_requests,
+                metrics.total_wait.as_secs()
+            );
             let sleep_duration = Duration::from_secs(86_000);
             sleep(
                 if let Some(remaining_time) = sleep_duration.checked_sub(starting_time.elapsed()) {
@@ -64,6 +80,12 @@ async fn main() -> TaskResult<()> {
         println!("3: Update collection.");
         println!("4: Check files and update collection.");
         println!("5: Full setup.");
+        println!("6: Check star ratings.");
+        println!("7: Check star ratings and reprocess mismatched beatmaps.");
+        println!("8: Recompress stored files.");
+        println!("9: Report orphaned and missing files.");
+        println!("10: Delete orphaned files and report missing files.");
+        println!("11: Reset the ranked beatmaps cursor.");
         let mut input = String::new();
         stdin().read_line(&mut input)?;
         match input.trim_end() {
@@ -78,6 +100,16 @@ async fn main() -> TaskResult<()> {
                 setup_files(true).await?;
                 update_collection(current_time, Services::setup().await?).await?
             }
+            "6" => {
+                check_star_ratings(false, STAR_RATING_SAMPLE, Services::setup().await?).await?;
+            }
+            "7" => {
+                check_star_ratings(true, STAR_RATING_SAMPLE, Services::setup().await?).await?;
+            }
+            "8" => recompress_files(Services::setup().await?).await?,
+            "9" => reconcile_files(true, Services::setup().await?).await?,
+            "10" => reconcile_files(false, Services::setup().await?).await?,
+            "11" => reset_cursor(RANKED_CURSOR, Services::setup().await?).await?,
             _ => println!("Incorrect input."),
         }
     };
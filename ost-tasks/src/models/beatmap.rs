@@ -2,7 +2,7 @@ use ost_utils::{beatmaps_processor, osu_api};
 use sqlx::{
     query, query_as,
     types::chrono::{DateTime, Utc},
-    FromRow, Pool, Postgres, Type,
+    Executor, FromRow, Pool, Postgres, Type,
 };
 
 use crate::TaskResult;
@@ -15,12 +15,15 @@ pub struct Beatmap {
     pub bpm: i16,
     pub checksum: String,
     pub circle_size: f32,
+    pub difficulty_name: String,
     pub difficulty_rating: f32,
     pub favorite_count: i32,
     pub id: i32,
     pub last_updated: DateTime<Utc>,
     pub length: i16,
     pub longest_stream: i16,
+    #[sqlx(flatten)]
+    pub metadata: BeatmapsetMetadata,
     pub performance_100: i16,
     pub performance_95: i16,
     pub play_count: i32,
@@ -29,6 +32,15 @@ pub struct Beatmap {
     pub streams_length: i16,
     pub streams_spacing: f32,
     pub title: String,
+    pub title_unicode: String,
+}
+
+/// The stored star ratings of a beatmap, with and without double time.
+#[derive(FromRow)]
+pub struct BeatmapDifficulty {
+    pub difficulty_rating: f32,
+    pub double_time_difficulty_rating: f32,
+    pub id: i32,
 }
 
 #[derive(FromRow)]
@@ -38,6 +50,49 @@ pub struct BeatmapLastUpdated {
     pub ranked_status: RankedStatus,
 }
 
+/// The metadata shared by every beatmap of a beatmapset.
+#[derive(FromRow)]
+pub struct BeatmapsetMetadata {
+    pub artist: String,
+    pub artist_unicode: String,
+    pub average_bpm: Option<f32>,
+    pub card_url: String,
+    pub cover_url: String,
+    pub creator: String,
+    pub creator_id: i32,
+    pub genre: Option<String>,
+    pub language: Option<String>,
+    pub list_url: String,
+    pub nsfw: bool,
+    pub source: String,
+    pub tags: Option<String>,
+    pub video: bool,
+}
+
+impl From<&osu_api::models::Beatmapset> for BeatmapsetMetadata {
+    fn from(beatmapset: &osu_api::models::Beatmapset) -> Self {
+        Self {
+            artist: beatmapset.artist.clone(),
+            artist_unicode: beatmapset.artist_unicode.clone(),
+            average_bpm: beatmapset.bpm,
+            card_url: beatmapset.covers.card.clone(),
+            cover_url: beatmapset.covers.cover.clone(),
+            creator: beatmapset.creator.clone(),
+            creator_id: beatmapset.user_id,
+            genre: beatmapset.genre.as_ref().map(|genre| genre.name.clone()),
+            language: beatmapset
+                .language
+                .as_ref()
+                .map(|language| language.name.clone()),
+            list_url: beatmapset.covers.list.clone(),
+            nsfw: beatmapset.nsfw,
+            source: beatmapset.source.clone(),
+            tags: Some(beatmapset.tags.clone()).filter(|tags| !tags.is_empty()),
+            video: beatmapset.video,
+        }
+    }
+}
+
 #[derive(FromRow)]
 pub struct DoubleTimeBeatmap {
     pub accuracy: f32,
@@ -49,14 +104,38 @@ pub struct DoubleTimeBeatmap {
     pub performance_95: i16,
 }
 
-#[derive(Clone, Copy, Type)]
+#[derive(Clone, Copy, Eq, PartialEq, Type)]
 #[sqlx(type_name = "enum_ranked_status", rename_all = "snake_case")]
 pub enum RankedStatus {
+    Approved,
     Loved,
+    Qualified,
     Ranked,
     Unranked,
 }
 
+impl RankedStatus {
+    /// Returns whether the beatmap is stored as part of the ranked beatmaps, as opposed to a
+    /// submission.
+    pub fn is_ranked(self) -> bool {
+        matches!(self, Self::Ranked | Self::Approved)
+    }
+}
+
+impl From<osu_api::models::RankedStatus> for RankedStatus {
+    fn from(ranked_status: osu_api::models::RankedStatus) -> Self {
+        match ranked_status {
+            osu_api::models::RankedStatus::Approved => Self::Approved,
+            osu_api::models::RankedStatus::Loved => Self::Loved,
+            osu_api::models::RankedStatus::Qualified => Self::Qualified,
+            osu_api::models::RankedStatus::Ranked => Self::Ranked,
+            osu_api::models::RankedStatus::Graveyard
+            | osu_api::models::RankedStatus::Pending
+            | osu_api::models::RankedStatus::Wip => Self::Unranked,
+        }
+    }
+}
+
 pub async fn create_or_update(
     database: &Pool<Postgres>,
     beatmap: Beatmap,
@@ -68,13 +147,15 @@ pub async fn create_or_update(
             (accuracy, approach_rate, beatmapset_id, bpm, checksum, circle_size,
                 difficulty_rating, favorite_count, id, last_updated, length,
                 longest_stream, performance_100, performance_95, play_count,
-                ranked_status, streams_density, streams_length, streams_spacing, title)
+                ranked_status, streams_density, streams_length, streams_spacing, title,
+                title_unicode, difficulty_name)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13,
-                $14, $15, $16, $17, $18, $19, $20)
+                $14, $15, $16, $17, $18, $19, $20, $21, $22)
             ON CONFLICT (id) DO
             UPDATE SET bpm = $4, difficulty_rating = $7, favorite_count = $8,
                 performance_100 = $13, performance_95 = $14, play_count = $15,
-                streams_density = $17, streams_length = $18, streams_spacing = $19"#,
+                ranked_status = $16, streams_density = $17, streams_length = $18,
+                streams_spacing = $19, title_unicode = $21, difficulty_name = $22"#,
     )
     .bind(beatmap.accuracy)
     .bind(beatmap.approach_rate)
@@ -96,8 +177,11 @@ pub async fn create_or_update(
     .bind(beatmap.streams_length)
     .bind(beatmap.streams_spacing)
     .bind(&beatmap.title)
+    .bind(&beatmap.title_unicode)
+    .bind(&beatmap.difficulty_name)
     .execute(&mut transaction)
     .await?;
+    update_metadata(&mut transaction, beatmap.id, &beatmap.metadata).await?;
     query(
         r#"INSERT INTO double_time_beatmaps
             (accuracy, approach_rate, bpm, circle_size, difficulty_rating, id,
@@ -108,7 +192,7 @@ pub async fn create_or_update(
                 $13, $14, $15, $16)
             ON CONFLICT (id) DO
             UPDATE SET bpm = $3, difficulty_rating = $5, performance_100 = $10,
-                performance_95 = $11, streams_density = $13,
+                performance_95 = $11, ranked_status = $12, streams_density = $13,
                 streams_length = $14, streams_spacing = $15"#,
     )
     .bind(double_time_beatmap.accuracy)
@@ -163,6 +247,7 @@ pub fn parse_beatmap(
             bpm: beatmap_statistics.bpm.no_modification,
             checksum: beatmap.checksum.clone(),
             circle_size: beatmap_statistics.circle_size,
+            difficulty_name: beatmap.version.clone(),
             difficulty_rating: beatmap_statistics.difficulty_rating.no_modification,
             favorite_count: beatmapset.favourite_count,
             last_updated: if let Some(ranked_date) = beatmapset.ranked_date {
@@ -172,20 +257,16 @@ pub fn parse_beatmap(
             },
             length: beatmap.total_length,
             longest_stream: beatmap_statistics.longest_stream,
+            metadata: beatmapset.into(),
             performance_100: beatmap_statistics.performance_100.no_modification,
             performance_95: beatmap_statistics.performance_95.no_modification,
             play_count: beatmapset.play_count,
-            ranked_status: if beatmap.is_ranked() {
-                RankedStatus::Ranked
-            } else if beatmap.is_loved() {
-                RankedStatus::Loved
-            } else {
-                RankedStatus::Unranked
-            },
+            ranked_status: beatmap.ranked.into(),
             streams_density: beatmap_statistics.streams_density,
             streams_length: beatmap_statistics.streams_length,
             streams_spacing: beatmap_statistics.streams_spacing,
             title: format!("{} [{}]", &beatmapset.title, &beatmap.version),
+            title_unicode: format!("{} [{}]", &beatmapset.title_unicode, &beatmap.version),
         },
         DoubleTimeBeatmap {
             accuracy: beatmap_statistics.accuracy.double_time,
@@ -199,6 +280,18 @@ pub fn parse_beatmap(
     )
 }
 
+/// Checks whether a beatmap or a submission with the specified `id` exists.
+pub async fn exists(database: &Pool<Postgres>, id: i32) -> TaskResult<bool> {
+    let (exists,) = query_as::<_, (bool,)>(
+        r#"SELECT EXISTS(SELECT 1 FROM beatmaps WHERE id = $1)
+            OR EXISTS(SELECT 1 FROM submissions WHERE id = $1)"#,
+    )
+    .bind(id)
+    .fetch_one(database)
+    .await?;
+    Ok(exists)
+}
+
 pub async fn retrieve_all(database: &Pool<Postgres>) -> TaskResult<Vec<BeatmapLastUpdated>> {
     Ok(
         query_as::<_, BeatmapLastUpdated>(
@@ -209,18 +302,77 @@ pub async fn retrieve_all(database: &Pool<Postgres>) -> TaskResult<Vec<BeatmapLa
     )
 }
 
+/// Retrieves the star ratings of up to `size` beatmaps chosen at random.
+pub async fn retrieve_sample(
+    database: &Pool<Postgres>,
+    size: i64,
+) -> TaskResult<Vec<BeatmapDifficulty>> {
+    Ok(query_as::<_, BeatmapDifficulty>(
+        r#"SELECT beatmaps.difficulty_rating,
+                double_time_beatmaps.difficulty_rating AS double_time_difficulty_rating, beatmaps.id
+            FROM beatmaps INNER JOIN double_time_beatmaps ON beatmaps.id = double_time_beatmaps.id
+            ORDER BY RANDOM() LIMIT $1"#,
+    )
+    .bind(size)
+    .fetch_all(database)
+    .await?)
+}
+
 pub async fn synchronize_attributes(
     database: &Pool<Postgres>,
     favorite_count: i32,
     id: i32,
+    metadata: &BeatmapsetMetadata,
     play_count: i32,
 ) -> TaskResult<()> {
+    let mut transaction = database.begin().await?;
     query(r#"UPDATE beatmaps SET favorite_count = $1, play_count = $2 WHERE id = $3"#)
         .bind(favorite_count)
         .bind(play_count)
         .bind(id)
-        .execute(database)
+        .execute(&mut transaction)
         .await?;
+    update_metadata(&mut transaction, id, metadata).await?;
+    transaction.commit().await?;
+    Ok(())
+}
+
+/// Updates the beatmapset metadata of a beatmap, keeping the stored genre, language, average BPM
+/// and tags when the osu! API omits them.
+async fn update_metadata<'c, E>(
+    executor: E,
+    id: i32,
+    metadata: &BeatmapsetMetadata,
+) -> TaskResult<()>
+where
+    E: Executor<'c, Database = Postgres>,
+{
+    query(
+        r#"UPDATE beatmaps
+            SET artist = $1, artist_unicode = $2, average_bpm = COALESCE($3, average_bpm),
+                card_url = $4, cover_url = $5, creator = $6, creator_id = $7,
+                genre = COALESCE($8, genre), language = COALESCE($9, language),
+                list_url = $10, nsfw = $11, source = $12, tags = COALESCE($13, tags),
+                video = $14
+            WHERE id = $15"#,
+    )
+    .bind(&metadata.artist)
+    .bind(&metadata.artist_unicode)
+    .bind(metadata.average_bpm)
+    .bind(&metadata.card_url)
+    .bind(&metadata.cover_url)
+    .bind(&metadata.creator)
+    .bind(metadata.creator_id)
+    .bind(&metadata.genre)
+    .bind(&metadata.language)
+    .bind(&metadata.list_url)
+    .bind(metadata.nsfw)
+    .bind(&metadata.source)
+    .bind(&metadata.tags)
+    .bind(metadata.video)
+    .bind(id)
+    .execute(executor)
+    .await?;
     Ok(())
 }
 
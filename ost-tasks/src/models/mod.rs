@@ -3,6 +3,7 @@ use sqlx::FromRow;
 pub mod beatmap;
 pub mod submission;
 pub mod unchecked_submission;
+pub mod user;
 
 #[derive(FromRow)]
 pub struct Submission {
@@ -64,6 +64,12 @@ pub async fn resubmit(database: &Pool<Postgres>, id: i32, title: &str) -> TaskRe
     Ok(())
 }
 
+pub async fn retrieve_all(database: &Pool<Postgres>) -> TaskResult<Vec<Submission>> {
+    Ok(query_as::<_, Submission>(r#"SELECT id FROM submissions"#)
+        .fetch_all(database)
+        .await?)
+}
+
 pub async fn retrieve_all_processing(database: &Pool<Postgres>) -> TaskResult<Vec<Submission>> {
     Ok(
         query_as::<_, Submission>(r#"SELECT id FROM submissions WHERE approval_status = $1"#)
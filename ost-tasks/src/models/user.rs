@@ -0,0 +1,32 @@
+use crate::TaskResult;
+use sqlx::{query, query_as, FromRow, Pool, Postgres};
+
+#[derive(FromRow)]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+}
+
+pub async fn retrieve_all(database: &Pool<Postgres>) -> TaskResult<Vec<User>> {
+    Ok(query_as::<_, User>(r#"SELECT id, username FROM users"#)
+        .fetch_all(database)
+        .await?)
+}
+
+/// Updates the username of the user with the specified `id`, renaming any other user that had the
+/// same username before to the `#<id>` placeholder until its own username is synchronized.
+pub async fn update_username(database: &Pool<Postgres>, id: i32, username: &str) -> TaskResult<()> {
+    let mut transaction = database.begin().await?;
+    query(r#"UPDATE users SET username = CONCAT('#', id) WHERE username = $1 AND id != $2"#)
+        .bind(username)
+        .bind(id)
+        .execute(&mut transaction)
+        .await?;
+    query(r#"UPDATE users SET username = $1 WHERE id = $2"#)
+        .bind(username)
+        .bind(id)
+        .execute(&mut transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(())
+}
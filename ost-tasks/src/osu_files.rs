@@ -20,11 +20,11 @@ impl Client {
         let request = get(format!("https://osu.ppy.sh/osu/{id}")).await;
         *rate_limiter = Instant::now();
         drop(rate_limiter);
-        let file = request?.text().await?;
+        let file = request?.bytes().await?;
         Ok(if file.is_empty() {
             None
         } else {
-            Some(Vec::from(file.as_bytes()))
+            Some(file.to_vec())
         })
     }
 }
@@ -0,0 +1,253 @@
+use super::{create_beatmap, Services, Tasks, THREADS};
+use crate::{
+    models::{beatmap, submission},
+    TaskResult,
+};
+use ost_utils::{
+    osu_api,
+    storage::{self, models::Cursor},
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// The mods bitflags of double time.
+const DOUBLE_TIME: u32 = 64;
+/// The largest difference between the star ratings of osu! and the stored ones still considered a
+/// match, since the stored ones are rounded to two decimals.
+const STAR_RATING_TOLERANCE: f32 = 0.05;
+
+/// A stored star rating that differs from the one computed by osu!.
+pub struct StarRatingMismatch {
+    pub double_time: bool,
+    pub id: i32,
+    pub official: f32,
+    pub stored: f32,
+}
+
+async fn check_star_rating(
+    beatmap: beatmap::BeatmapDifficulty,
+    mut osu_api: osu_api::Client,
+) -> TaskResult<Vec<StarRatingMismatch>> {
+    let mut mismatches = Vec::new();
+    for (double_time, stored) in [
+        (false, beatmap.difficulty_rating),
+        (true, beatmap.double_time_difficulty_rating),
+    ] {
+        let mods = if double_time { DOUBLE_TIME } else { 0 };
+        let Some(attributes) = osu_api
+            .retrieve_beatmap_attributes(beatmap.id, mods)
+            .await?
+        else {
+            break;
+        };
+        if (attributes.star_rating - stored).abs() > STAR_RATING_TOLERANCE {
+            mismatches.push(StarRatingMismatch {
+                double_time,
+                id: beatmap.id,
+                official: attributes.star_rating,
+                stored,
+            });
+        }
+    }
+    Ok(mismatches)
+}
+
+/// Compares the stored star ratings of up to `sample_size` random beatmaps with the ones computed
+/// by osu!, logging the mismatches and processing their beatmaps again if `reprocess` is set.
+pub async fn check_star_ratings(
+    reprocess: bool,
+    sample_size: i64,
+    services: Services,
+) -> TaskResult<Vec<StarRatingMismatch>> {
+    tracing::info!("Checking star ratings: start");
+    let beatmaps = beatmap::retrieve_sample(&services.database, sample_size).await?;
+    let sampled = beatmaps.len();
+    let mut tasks = Tasks::new(THREADS, sampled, true, Some("star ratings checked"));
+    let mut mismatches = Vec::new();
+    for beatmap in beatmaps {
+        if let Some(results) = tasks
+            .spawn(check_star_rating(beatmap, services.osu_api.clone()))
+            .await?
+        {
+            mismatches.extend(results.into_iter().flatten());
+        }
+    }
+    for mismatch in &mismatches {
+        tracing::warn!(
+            "Beatmap {}{}: osu! {:.2} stars, stored {:.2} stars",
+            mismatch.id,
+            if mismatch.double_time { " +DT" } else { "" },
+            mismatch.official,
+            mismatch.stored
+        );
+    }
+    let ids: BTreeSet<i32> = mismatches.iter().map(|mismatch| mismatch.id).collect();
+    tracing::info!(
+        "{} of {sampled} beatmaps have mismatched star ratings",
+        ids.len()
+    );
+    if reprocess {
+        let mut tasks = Tasks::new(
+            THREADS,
+            ids.len(),
+            true,
+            Some("mismatched beatmaps processed"),
+        );
+        for id in ids {
+            tasks.spawn(reprocess_beatmap(id, services.clone())).await?;
+        }
+    }
+    tracing::info!("Checking star ratings: end");
+    Ok(mismatches)
+}
+
+/// Uploads the stored file of a beatmap again if it is not compressed with the compression of the
+/// storage client, returning whether it was uploaded again.
+async fn recompress_file(id: i32, storage: storage::Client) -> TaskResult<bool> {
+    match storage.recompress(storage::beatmap_key(id)).await {
+        Err(storage::Error::NotFound(_)) => {
+            tracing::warn!("Beatmap {id} has no stored file");
+            Ok(false)
+        }
+        Err(storage::Error::PreconditionFailed(_)) => {
+            tracing::warn!("Beatmap {id} was replaced while being recompressed");
+            Ok(false)
+        }
+        result => Ok(result?),
+    }
+}
+
+/// Compresses the stored files of every beatmap in place with the compression of the storage
+/// client, set by the `STORAGE_COMPRESSION` environment variable.
+///
+/// Files already compressed with it are left untouched, so the task can be resumed.
+pub async fn recompress_files(services: Services) -> TaskResult<()> {
+    tracing::info!("Recompressing files: start");
+    let beatmaps = beatmap::retrieve_all(&services.database).await?;
+    let mut tasks = Tasks::new(THREADS, beatmaps.len(), true, Some("files checked"));
+    let mut recompressed = 0;
+    for beatmap in beatmaps {
+        if let Some(results) = tasks
+            .spawn(recompress_file(beatmap.id, services.storage.clone()))
+            .await?
+        {
+            recompressed += results.into_iter().filter(|result| *result).count();
+        }
+    }
+    tracing::info!("Recompressing files: end, {recompressed} files recompressed");
+    Ok(())
+}
+
+/// Compares the stored beatmap files with the `beatmaps` and `submissions` tables, logging the
+/// beatmaps without a file and the orphaned files, which are deleted unless `dry_run` is set.
+///
+/// Each orphaned file is checked against the database again right before deleting it, since new
+/// beatmaps are uploaded before their rows are inserted, and objects that are not beatmap files are
+/// never deleted.
+pub async fn reconcile_files(dry_run: bool, services: Services) -> TaskResult<()> {
+    tracing::info!("Reconciling files: start");
+    let keys = services.storage.list_all("beatmaps/").await?;
+    let stored: HashSet<i32> = keys.iter().filter_map(|key| stored_id(key)).collect();
+    let beatmaps: BTreeSet<i32> = beatmap::retrieve_all(&services.database)
+        .await?
+        .into_iter()
+        .map(|beatmap| beatmap.id)
+        .collect();
+    let submissions: HashSet<i32> = submission::retrieve_all(&services.database)
+        .await?
+        .into_iter()
+        .map(|submission| submission.id)
+        .collect();
+    let missing: Vec<i32> = beatmaps
+        .iter()
+        .filter(|id| !stored.contains(id))
+        .copied()
+        .collect();
+    let orphans: Vec<String> = keys
+        .into_iter()
+        .filter(|key| {
+            !matches!(stored_id(key), Some(id) if beatmaps.contains(&id) || submissions.contains(&id))
+        })
+        .collect();
+    for id in &missing {
+        tracing::warn!("Beatmap {id} has no stored file");
+    }
+    for key in &orphans {
+        tracing::warn!("Storage object {key} is orphaned");
+    }
+    tracing::info!(
+        "{} of {} beatmaps have no stored file, {} stored files are orphaned",
+        missing.len(),
+        beatmaps.len(),
+        orphans.len()
+    );
+    if !dry_run {
+        let deletable: Vec<(i32, String)> = orphans
+            .into_iter()
+            .filter_map(|key| Some((stored_id(&key)?, key)))
+            .collect();
+        let mut tasks = Tasks::new(
+            THREADS,
+            deletable.len(),
+            true,
+            Some("orphaned files deleted"),
+        );
+        for (id, key) in deletable {
+            tasks
+                .spawn(delete_orphan(id, key, services.clone()))
+                .await?;
+        }
+    }
+    tracing::info!("Reconciling files: end");
+    Ok(())
+}
+
+/// Restarts the crawl of the cursor with the specified `name`, replacing it even if it is corrupt.
+pub async fn reset_cursor(name: &str, services: Services) -> TaskResult<()> {
+    let mut cursor = Cursor::retrieve(&services.storage, name, true).await?;
+    cursor.approved_date = 0;
+    cursor.cursor_string = None;
+    cursor.update(&services.storage).await?;
+    tracing::info!("Reset cursor {name} at version {}", cursor.version);
+    Ok(())
+}
+
+/// Returns the ID of the beatmap whose file is stored with the `key`, if it is a beatmap file.
+/// Deletes the stored file of the beatmap with the specified `id` unless it was added to the
+/// database after the file was listed.
+async fn delete_orphan(id: i32, key: String, services: Services) -> TaskResult<()> {
+    if beatmap::exists(&services.database, id).await? {
+        tracing::info!("Keeping storage object {key}: beatmap {id} was added meanwhile");
+        return Ok(());
+    }
+    Ok(services.storage.delete(key).await?)
+}
+
+fn stored_id(key: &str) -> Option<i32> {
+    key.strip_prefix("beatmaps/")?
+        .strip_suffix(".osu")?
+        .parse()
+        .ok()
+}
+
+/// Processes a stored beatmap again from its stored file.
+async fn reprocess_beatmap(id: i32, mut services: Services) -> TaskResult<()> {
+    let Some(beatmap) = services.osu_api.retrieve_beatmap(id).await? else {
+        tracing::warn!("Beatmap {id} no longer exists in the osu! API");
+        return Ok(());
+    };
+    let file = match services
+        .storage
+        .retrieve_beatmap(Some(&beatmap.checksum), id)
+        .await
+    {
+        Err(error @ storage::Error::ChecksumMismatch { .. }) => {
+            tracing::warn!("Skipping beatmap {id}: {error}");
+            return Ok(());
+        }
+        result => result?,
+    };
+    if !create_beatmap(&beatmap, &services.database, &file).await? {
+        tracing::warn!("Beatmap {id} no longer has enough streams");
+    }
+    Ok(())
+}
@@ -1,16 +1,26 @@
 use crate::{models::beatmap, osu_files, TaskResult};
 use ost_utils::{
     beatmaps_processor::process_beatmap,
-    osu_api::{self, models::Beatmap},
+    osu_api::{
+        self,
+        cache::{CacheTtls, Endpoint, MemoryCache},
+        models::{Beatmap, Mode, SearchPage, SearchQuery, SearchSort},
+    },
     storage,
 };
+use redis::aio::ConnectionManager;
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
-use std::{env, future::Future};
+use std::{env, future::Future, time::Duration};
 use tokio::{spawn, task::JoinHandle};
 
+pub mod maintenance;
 pub mod periodic;
 pub mod setup;
 
+/// The maximum number of osu! API responses cached during a run of the tasks.
+const CACHE_CAPACITY: usize = 50_000;
+/// The time the osu! API responses are cached for, roughly the length of a run of the tasks.
+const CACHE_TTL: Duration = Duration::from_secs(3_600);
 const REQUESTS_RATE: usize = 300;
 const THREADS: usize = 64;
 
@@ -29,12 +39,74 @@ impl Services {
                 .connect(&env::var("DATABASE_URL")?)
                 .await?,
             storage: storage::Client::from_environment().await?,
-            osu_api: osu_api::Client::from_environment(REQUESTS_RATE)?,
+            osu_api: match env::var("REDIS_URL") {
+                Ok(url) if !url.is_empty() => {
+                    osu_api::Client::from_environment_with_shared_rate_limiter(
+                        ConnectionManager::new(redis::Client::open(url)?).await?,
+                        REQUESTS_RATE,
+                    )?
+                }
+                _ => osu_api::Client::from_environment(REQUESTS_RATE)?,
+            }
+            .with_cache(
+                MemoryCache::new(CACHE_CAPACITY),
+                CacheTtls::default()
+                    .with(Endpoint::Beatmap, CACHE_TTL)
+                    .with(Endpoint::Beatmaps, CACHE_TTL)
+                    .with(Endpoint::Beatmapset, CACHE_TTL),
+            ),
             osu_files: osu_files::Client::default(),
         })
     }
 }
 
+/// The osu!standard beatmaps of a page of ranked beatmapsets, up to a limit date, skipping the
+/// beatmapsets without a ranked date.
+pub struct RankedPage {
+    /// The ranked date in milliseconds of the last beatmapset included, if any.
+    pub approved_date: Option<i64>,
+    pub beatmaps: Vec<Beatmap>,
+    /// The cursor of the next page, or `None` if the search is finished, either because there are
+    /// no more pages or because the limit date was reached within this page.
+    pub next_cursor_string: Option<String>,
+}
+
+impl RankedPage {
+    pub fn new(page: SearchPage, limit_date: i64) -> Self {
+        let mut ranked_page = Self {
+            approved_date: None,
+            beatmaps: Vec::new(),
+            next_cursor_string: page.cursor_string,
+        };
+        for beatmapset in page.beatmapsets {
+            let Some(ranked_date) = beatmapset.ranked_date else {
+                tracing::warn!("Beatmapset {} has no ranked date", beatmapset.id);
+                continue;
+            };
+            let approved_date = ranked_date.timestamp_millis();
+            if approved_date > limit_date {
+                ranked_page.next_cursor_string = None;
+                break;
+            }
+            ranked_page.approved_date = Some(approved_date);
+            ranked_page
+                .beatmaps
+                .append(&mut beatmapset.into_standard_beatmaps());
+        }
+        ranked_page
+    }
+
+    /// Returns the query of the osu!standard beatmapsets with a leaderboard sorted by ranked date,
+    /// starting from `cursor_string`.
+    pub fn query(cursor_string: Option<String>) -> SearchQuery {
+        SearchQuery::default()
+            .mode(Mode::Osu)
+            .nsfw(true)
+            .sort(SearchSort::RankedAsc)
+            .cursor_string(cursor_string)
+    }
+}
+
 pub struct Tasks<T> {
     current: usize,
     limit: usize,
@@ -56,11 +128,10 @@ where
     }
 
     pub fn log(&self) {
-        if self.unit.is_some() && self.log_progress {
+        if let (Some(unit), true) = (self.unit, self.log_progress) {
             tracing::info!(
-                "{:.2}% {}.",
-                100.0 * self.current as f64 / self.limit as f64,
-                self.unit.unwrap()
+                "{:.2}% {unit}.",
+                100.0 * self.current as f64 / self.limit as f64
             );
         }
     }
@@ -95,6 +166,27 @@ where
     }
 }
 
+/// Downloads the file of a beatmap, verifying it against the checksum returned by the osu! API.
+///
+/// Returns `None` if the file is missing or does not match the checksum, e.g., because the download
+/// was truncated or returned an error page.
+pub async fn download_beatmap(
+    beatmap: &Beatmap,
+    osu_files: &mut osu_files::Client,
+) -> TaskResult<Option<Vec<u8>>> {
+    let Some(file) = osu_files.download(beatmap.id).await? else {
+        tracing::warn!("Skipping beatmap {}: file not found", beatmap.id);
+        return Ok(None);
+    };
+    match storage::verify_checksum(&file, &beatmap.checksum, &storage::beatmap_key(beatmap.id)) {
+        Ok(_) => Ok(Some(file)),
+        Err(error) => {
+            tracing::warn!("Skipping beatmap {}: {error}", beatmap.id);
+            Ok(None)
+        }
+    }
+}
+
 pub async fn create_beatmap(
     beatmap: &Beatmap,
     database: &Pool<Postgres>,
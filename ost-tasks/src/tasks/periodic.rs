@@ -1,11 +1,17 @@
-use super::{create_beatmap, log_date_progress, Services, Tasks, THREADS};
+use super::{
+    create_beatmap, download_beatmap, log_date_progress, RankedPage, Services, Tasks, THREADS,
+};
 use crate::{
-    models::{beatmap, submission, unchecked_submission},
+    models::{beatmap, submission, unchecked_submission, user},
     osu_files, TaskResult,
 };
+use futures::TryStreamExt;
 use ost_utils::{
     osu_api::{self, models::Beatmap, IDS_LIMIT},
-    storage::{self, models::Cursor},
+    storage::{
+        self,
+        models::{Cursor, RANKED_CURSOR},
+    },
 };
 use sqlx::{
     types::chrono::{DateTime, Utc},
@@ -20,9 +26,11 @@ pub async fn add_beatmap(
     storage: storage::Client,
     mut osu_files: osu_files::Client,
 ) -> TaskResult<()> {
-    let file = osu_files.download(beatmap.id).await?.unwrap();
+    let Some(file) = download_beatmap(&beatmap, &mut osu_files).await? else {
+        return Ok(());
+    };
     storage
-        .upload(&file, format!("beatmaps/{}.osu", beatmap.id))
+        .upload_beatmap(Some(&beatmap.checksum), &file, beatmap.id)
         .await?;
     if beatmap.is_ranked() {
         submission::delete(&database, beatmap.id).await?;
@@ -31,17 +39,17 @@ pub async fn add_beatmap(
     Ok(())
 }
 
-pub async fn add_beatmaps(limit_date: i64, mut services: Services) -> TaskResult<()> {
+pub async fn add_beatmaps(limit_date: i64, services: Services) -> TaskResult<()> {
     tracing::info!("Adding ranked beatmaps: start");
-    let mut cursor = Cursor::retrieve(&services.storage).await?;
+    let mut cursor = Cursor::retrieve(&services.storage, RANKED_CURSOR, false).await?;
     let start = cursor.approved_date;
-    loop {
-        let (beatmaps, approved_date, id) = services
-            .osu_api
-            .retrieve_leaderboard_beatmaps(cursor.approved_date, cursor.id, limit_date)
-            .await?;
-        let mut tasks = Tasks::new(beatmaps.len(), beatmaps.len(), false, None);
-        for beatmap in beatmaps {
+    let mut pages = services
+        .osu_api
+        .search_beatmapset_pages(RankedPage::query(cursor.cursor_string.clone()));
+    while let Some(page) = pages.try_next().await? {
+        let page = RankedPage::new(page, limit_date);
+        let mut tasks = Tasks::new(page.beatmaps.len(), page.beatmaps.len(), false, None);
+        for beatmap in page.beatmaps {
             tasks
                 .spawn(add_beatmap(
                     beatmap,
@@ -51,19 +59,21 @@ pub async fn add_beatmaps(limit_date: i64, mut services: Services) -> TaskResult
                 ))
                 .await?;
         }
+        cursor.approved_date = page.approved_date.unwrap_or(cursor.approved_date);
         log_date_progress(
-            approved_date,
+            cursor.approved_date,
             limit_date,
             false,
             start,
             "ranked beatmaps processed",
         );
-        cursor = Cursor::new(approved_date, id);
-        cursor.update(&services.storage).await?;
-        if approved_date >= limit_date {
+        if page.next_cursor_string.is_none() {
             break;
         }
+        cursor.cursor_string = page.next_cursor_string;
+        cursor.update(&services.storage).await?;
     }
+    cursor.update(&services.storage).await?;
     tracing::info!("Adding ranked beatmaps: end");
     Ok(())
 }
@@ -122,8 +132,15 @@ async fn check_beatmapset(
     id: i32,
     mut osu_api: osu_api::Client,
 ) -> TaskResult<()> {
-    if let Some(beatmapset) = osu_api.retrieve_beatmapset(id.abs()).await? {
-        let beatmaps = beatmapset.beatmaps.unwrap();
+    let beatmapset = match osu_api.retrieve_beatmapset(id.abs()).await {
+        Err(error @ osu_api::Error::Decode { .. }) => {
+            tracing::warn!("Skipping beatmapset {}: {error}", id.abs());
+            return Ok(());
+        }
+        result => result?,
+    };
+    if let Some(beatmapset) = beatmapset {
+        let beatmaps = beatmapset.beatmaps.unwrap_or_default();
         let mut tasks = Tasks::new(beatmaps.len(), beatmaps.len(), false, None);
         for beatmap in beatmaps {
             if beatmap.is_ranked() {
@@ -191,11 +208,13 @@ pub async fn check_submissions(services: Services) -> TaskResult<()> {
 }
 
 async fn process_submission(mut services: Services, beatmap: Beatmap) -> TaskResult<()> {
-    let file = services.osu_files.download(beatmap.id).await?.unwrap();
+    let Some(file) = download_beatmap(&beatmap, &mut services.osu_files).await? else {
+        return Ok(());
+    };
     if !beatmap.is_ranked() && create_beatmap(&beatmap, &services.database, &file).await? {
         services
             .storage
-            .upload(&file, format!("beatmaps/{}.osu", beatmap.id))
+            .upload_beatmap(Some(&beatmap.checksum), &file, beatmap.id)
             .await?;
         submission::approve(&services.database, beatmap.id).await?;
         return Ok(());
@@ -243,11 +262,11 @@ pub async fn process_submissions(mut services: Services) -> TaskResult<()> {
 async fn synchronize_beatmap(
     beatmap: Beatmap,
     database: Pool<Postgres>,
-    last_updated_submission: Option<DateTime<Utc>>,
+    submission: Option<(DateTime<Utc>, beatmap::RankedStatus)>,
     storage: storage::Client,
 ) -> TaskResult<()> {
-    if let Some(last_updated) = last_updated_submission {
-        if last_updated < beatmap.last_updated {
+    if let Some((last_updated, ranked_status)) = submission {
+        if last_updated < beatmap.last_updated || ranked_status != beatmap.ranked.into() {
             let title = format!(
                 "{} [{}]",
                 &beatmap.beatmapset.unwrap().title,
@@ -265,6 +284,7 @@ async fn synchronize_beatmap(
         &database,
         beatmapset.favourite_count,
         beatmap.id,
+        &(&beatmapset).into(),
         beatmapset.play_count,
     )
     .await?;
@@ -283,8 +303,8 @@ pub async fn synchronize_with_osu_api(mut services: Services) -> TaskResult<()>
     );
     for chunk in beatmaps.chunks(IDS_LIMIT) {
         let ids = chunk.iter().map(|beatmap| {
-            if !matches!(beatmap.ranked_status, beatmap::RankedStatus::Ranked) {
-                submissions.insert(beatmap.id, beatmap.last_updated);
+            if !beatmap.ranked_status.is_ranked() {
+                submissions.insert(beatmap.id, (beatmap.last_updated, beatmap.ranked_status));
             }
             beatmap.id
         });
@@ -293,12 +313,12 @@ pub async fn synchronize_with_osu_api(mut services: Services) -> TaskResult<()>
             .retrieve_beatmaps(&ids.collect::<Vec<i32>>())
             .await?
         {
-            let last_updated = submissions.remove(&beatmap.id);
+            let submission = submissions.remove(&beatmap.id);
             tasks
                 .spawn(synchronize_beatmap(
                     beatmap,
                     services.database.clone(),
-                    last_updated,
+                    submission,
                     services.storage.clone(),
                 ))
                 .await?;
@@ -313,3 +333,23 @@ pub async fn synchronize_with_osu_api(mut services: Services) -> TaskResult<()>
     tracing::info!("Synchronizing with osu! API: end");
     Ok(())
 }
+
+pub async fn synchronize_users(mut services: Services) -> TaskResult<()> {
+    tracing::info!("Synchronizing users: start");
+    let users = user::retrieve_all(&services.database).await?;
+    for chunk in users.chunks(IDS_LIMIT) {
+        let usernames = chunk
+            .iter()
+            .map(|user| (user.id, user.username.as_str()))
+            .collect::<HashMap<i32, &str>>();
+        let ids = chunk.iter().map(|user| user.id).collect::<Vec<i32>>();
+        for osu_user in services.osu_api.retrieve_users(&ids).await? {
+            let username = osu_user.username.replace(' ', "_");
+            if usernames.get(&osu_user.id) != Some(&username.as_str()) {
+                user::update_username(&services.database, osu_user.id, &username).await?;
+            }
+        }
+    }
+    tracing::info!("Synchronizing users: end");
+    Ok(())
+}
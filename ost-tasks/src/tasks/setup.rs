@@ -1,13 +1,12 @@
-use super::{create_beatmap, Services, Tasks, THREADS};
-use crate::{
-    models::beatmap::{self, RankedStatus},
-    osu_files,
-    tasks::log_date_progress,
-    TaskResult,
-};
+use super::{create_beatmap, download_beatmap, RankedPage, Services, Tasks, THREADS};
+use crate::{models::beatmap, osu_files, tasks::log_date_progress, TaskResult};
+use futures::TryStreamExt;
 use ost_utils::{
     osu_api::{models::Beatmap, IDS_LIMIT},
-    storage::{self, models::Cursor},
+    storage::{
+        self,
+        models::{Cursor, RANKED_CURSOR},
+    },
 };
 use rosu_pp::GameMode;
 use sqlx::{Pool, Postgres};
@@ -26,12 +25,17 @@ async fn setup_file(storage: storage::Client, path: PathBuf, upload_file: bool)
     let file = read(&path).await?;
     if let Ok(beatmap) = rosu_pp::Beatmap::parse(file.as_ref()).await {
         if matches!(beatmap.mode, GameMode::Osu) && upload_file {
-            storage
-                .upload(
-                    &file,
-                    format!("beatmaps/{}", path.file_name().unwrap().to_str().unwrap()),
-                )
-                .await?;
+            match path.file_stem().and_then(OsStr::to_str).map(str::parse) {
+                Some(Ok(id)) => storage.upload_beatmap(None, &file, id).await?,
+                _ => {
+                    storage
+                        .upload(
+                            &file,
+                            format!("beatmaps/{}", path.file_name().unwrap().to_str().unwrap()),
+                        )
+                        .await?
+                }
+            }
         }
         return Ok(());
     }
@@ -62,19 +66,24 @@ async fn update_beatmap(
     storage: storage::Client,
     mut osu_files: osu_files::Client,
 ) -> TaskResult<i32> {
-    let file = if let Ok(file) = read(format!("./beatmaps/{}.osu", beatmap.id)).await {
+    let path = format!("./beatmaps/{}.osu", beatmap.id);
+    let local_file = read(&path)
+        .await
+        .ok()
+        .filter(|file| storage::verify_checksum(file, &beatmap.checksum, &path).is_ok());
+    let file = if let Some(file) = local_file {
         file
     } else if let Ok(file) = storage
-        .retrieve(format!("beatmaps/{}.osu", beatmap.id))
+        .retrieve_beatmap(Some(&beatmap.checksum), beatmap.id)
         .await
     {
-        write(format!("./beatmaps/{}.osu", beatmap.id), &file).await?;
+        write(&path, &file).await?;
         file
-    } else if let Some(file) = osu_files.download(beatmap.id).await? {
+    } else if let Some(file) = download_beatmap(&beatmap, &mut osu_files).await? {
         storage
-            .upload(&file, format!("beatmaps/{}.osu", beatmap.id))
+            .upload_beatmap(Some(&beatmap.checksum), &file, beatmap.id)
             .await?;
-        write(format!("./beatmaps/{}.osu", beatmap.id), &file).await?;
+        write(&path, &file).await?;
         file
     } else {
         return Ok(0);
@@ -88,16 +97,18 @@ async fn update_beatmap(
 async fn update_beatmaps(
     limit_date: i64,
     mut local_beatmaps: HashSet<i32>,
-    mut services: Services,
+    services: Services,
 ) -> TaskResult<()> {
-    let mut cursor = Cursor::new(0, 0);
-    loop {
-        let (beatmaps, approved_date, id) = services
-            .osu_api
-            .retrieve_leaderboard_beatmaps(cursor.approved_date, cursor.id, limit_date)
-            .await?;
-        let mut tasks = Tasks::new(beatmaps.len(), beatmaps.len(), true, None);
-        for beatmap in beatmaps {
+    let mut cursor = Cursor::retrieve(&services.storage, RANKED_CURSOR, true).await?;
+    cursor.approved_date = 0;
+    cursor.cursor_string = None;
+    let mut pages = services
+        .osu_api
+        .search_beatmapset_pages(RankedPage::query(None));
+    while let Some(page) = pages.try_next().await? {
+        let page = RankedPage::new(page, limit_date);
+        let mut tasks = Tasks::new(page.beatmaps.len(), page.beatmaps.len(), true, None);
+        for beatmap in page.beatmaps {
             if let Some(ids) = tasks
                 .spawn(update_beatmap(
                     beatmap,
@@ -112,17 +123,18 @@ async fn update_beatmaps(
                 });
             }
         }
+        cursor.approved_date = page.approved_date.unwrap_or(cursor.approved_date);
         log_date_progress(
-            approved_date,
+            cursor.approved_date,
             limit_date,
             true,
             1_191_710_791_000,
             "ranked beatmaps processed",
         );
-        cursor = Cursor::new(approved_date, id);
-        if approved_date >= limit_date {
+        if page.next_cursor_string.is_none() {
             break;
         }
+        cursor.cursor_string = page.next_cursor_string;
     }
     let mut tasks = Tasks::new(
         THREADS,
@@ -145,7 +157,7 @@ pub async fn update_collection(limit_date: i64, services: Services) -> TaskResul
     let mut ranked_beatmaps = HashSet::new();
     let mut submissions = Vec::new();
     for beatmap in beatmap::retrieve_all(&services.database).await? {
-        if let RankedStatus::Ranked = beatmap.ranked_status {
+        if beatmap.ranked_status.is_ranked() {
             ranked_beatmaps.insert(beatmap.id);
         } else {
             submissions.push(beatmap.id);
@@ -166,12 +178,14 @@ async fn update_submission(
     storage: storage::Client,
 ) -> TaskResult<()> {
     let file = if let Ok(file) = storage
-        .retrieve(format!("beatmaps/{}.osu", beatmap.id))
+        .retrieve_beatmap(Some(&beatmap.checksum), beatmap.id)
         .await
     {
         file
+    } else if let Some(file) = download_beatmap(&beatmap, &mut osu_files).await? {
+        file
     } else {
-        osu_files.download(beatmap.id).await?.unwrap()
+        return Ok(());
     };
     if !create_beatmap(&beatmap, &database, &file).await? {
         beatmap::delete(&database, beatmap.id, true).await?;
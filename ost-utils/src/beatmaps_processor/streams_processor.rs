@@ -112,7 +112,7 @@ pub fn process_beatmap(parsed_beatmap: &rosu_pp::Beatmap) -> Beatmap {
     beatmap
 }
 
-///
+/// Processes the intervals between consecutive hit objects of the beatmap, skipping spinners.
 fn process_intervals(beatmap: &mut Beatmap, parsed_beatmap: &rosu_pp::Beatmap) {
     let mut previous = None;
     let mut stream = Stream::default();
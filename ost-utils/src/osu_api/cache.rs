@@ -0,0 +1,175 @@
+use super::Error;
+use async_trait::async_trait;
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+use tokio::time::Instant;
+
+/// The osu! API endpoints whose responses can be cached by a [`ResponseCache`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Endpoint {
+    Beatmap,
+    Beatmaps,
+    Beatmapset,
+    Scores,
+    Search,
+    User,
+    Users,
+}
+
+/// The time the responses of each [`Endpoint`] are cached for, if any.
+#[derive(Clone, Debug, Default)]
+pub struct CacheTtls {
+    ttls: HashMap<Endpoint, Duration>,
+}
+
+impl CacheTtls {
+    /// Returns the TTL of the `endpoint`, if its responses are cached.
+    pub fn get(&self, endpoint: Endpoint) -> Option<Duration> {
+        self.ttls.get(&endpoint).copied()
+    }
+
+    /// Caches the responses of the `endpoint` for `ttl`.
+    pub fn with(mut self, endpoint: Endpoint, ttl: Duration) -> Self {
+        self.ttls.insert(endpoint, ttl);
+        self
+    }
+}
+
+/// Stores the bodies of successful responses of the osu! API [`Client`](super::Client), keyed by
+/// their path.
+#[async_trait]
+pub trait ResponseCache: Send + Sync {
+    /// Returns the cached body of the response for `key`, if it has not expired.
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error>;
+
+    /// Caches the `body` of the response for `key` for `ttl`.
+    async fn set(&self, key: &str, body: &[u8], ttl: Duration) -> Result<(), Error>;
+}
+
+/// A [`ResponseCache`] stored in the memory of the process, holding up to `capacity` responses.
+pub struct MemoryCache {
+    capacity: usize,
+    entries: Mutex<HashMap<String, (Instant, Vec<u8>)>>,
+}
+
+impl MemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl ResponseCache for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((expiration, body)) if Instant::now() < *expiration => Ok(Some(body.clone())),
+            Some(_) => {
+                entries.remove(key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    async fn set(&self, key: &str, body: &[u8], ttl: Duration) -> Result<(), Error> {
+        let mut entries = self.entries.lock().unwrap();
+        let now = Instant::now();
+        if entries.len() >= self.capacity && !entries.contains_key(key) {
+            entries.retain(|_, (expiration, _)| now < *expiration);
+            if entries.len() >= self.capacity {
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, (expiration, _))| *expiration)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(String::from(key), (now + ttl, body.to_vec()));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_cache::RedisCache;
+
+#[cfg(feature = "redis")]
+mod redis_cache {
+    use super::{Error, ResponseCache};
+    use async_trait::async_trait;
+    use redis::{aio::ConnectionManager, AsyncCommands};
+    use std::time::Duration;
+
+    /// A [`ResponseCache`] stored in Redis, shared by every process using the same `prefix`.
+    pub struct RedisCache {
+        connection: ConnectionManager,
+        prefix: String,
+    }
+
+    impl RedisCache {
+        /// Creates a new [`RedisCache`] whose keys start with `prefix`.
+        pub fn new(connection: ConnectionManager, prefix: impl Into<String>) -> Self {
+            Self {
+                connection,
+                prefix: prefix.into(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ResponseCache for RedisCache {
+        async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, Error> {
+            Ok(self
+                .connection
+                .clone()
+                .get(format!("{}:{key}", self.prefix))
+                .await?)
+        }
+
+        async fn set(&self, key: &str, body: &[u8], ttl: Duration) -> Result<(), Error> {
+            self.connection
+                .clone()
+                .set_ex::<_, _, ()>(
+                    format!("{}:{key}", self.prefix),
+                    body,
+                    ttl.as_secs().max(1) as usize,
+                )
+                .await?;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemoryCache, ResponseCache};
+    use std::{error::Error, time::Duration};
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_cache_expiration() -> Result<(), Box<dyn Error>> {
+        let cache = MemoryCache::new(2);
+        cache.set("a", b"a", Duration::from_secs(10)).await?;
+        cache.set("b", b"b", Duration::from_secs(20)).await?;
+        assert_eq!(cache.get("a").await?.as_deref(), Some(&b"a"[..]));
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(cache.get("a").await?.is_none());
+        assert_eq!(cache.get("b").await?.as_deref(), Some(&b"b"[..]));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_memory_cache_capacity() -> Result<(), Box<dyn Error>> {
+        let cache = MemoryCache::new(2);
+        cache.set("a", b"a", Duration::from_secs(10)).await?;
+        cache.set("b", b"b", Duration::from_secs(20)).await?;
+        cache.set("c", b"c", Duration::from_secs(30)).await?;
+        assert!(cache.get("a").await?.is_none());
+        assert!(cache.get("b").await?.is_some());
+        assert!(cache.get("c").await?.is_some());
+        Ok(())
+    }
+}
@@ -1,45 +1,161 @@
-use std::{env::VarError, error, fmt, num::ParseIntError};
-use tokio::sync::AcquireError;
+use super::transport::Response;
+use std::{env::VarError, error, fmt, num::ParseIntError, time::Duration};
+
+/// The number of characters of a response body kept in [`Error::Decode`].
+const BODY_SNIPPET_LENGTH: usize = 200;
 
 #[derive(Debug)]
 pub enum Error {
-    Acquire(AcquireError),
+    /// The response of an endpoint did not match the expected schema.
+    Decode {
+        body_snippet: String,
+        endpoint: String,
+        error: serde_json::Error,
+    },
+    /// The requested resource does not exist.
+    NotFound,
+    /// An environment variable is not a valid number.
     ParseInt(ParseIntError),
+    /// The Redis server of the shared rate limiter or the cache failed.
+    #[cfg(feature = "redis")]
+    Redis(redis::RedisError),
+    /// The osu! API rejected the request because of its rate limits.
+    RateLimited { retry_after: Option<Duration> },
+    /// The shared rate limiter is configured with a different `burst/rate`, the one included.
+    RateLimiterMismatch(String),
+    /// The request could not be performed, e.g., because of a network error.
     Reqwest(reqwest::Error),
+    /// The request failed after `attempts` attempts, the last one with `error`.
+    RetriesExhausted { attempts: u32, error: Box<Error> },
+    /// The body of a request could not be serialized.
+    SerdeJson(serde_json::Error),
+    /// The osu! API failed to handle the request.
+    Server(u16),
+    /// The osu! API responded with an unexpected status.
+    Status(u16),
+    /// The credentials or the token of the client were rejected.
+    Unauthorized,
+    /// The query string of a request could not be serialized.
+    UrlEncoded(serde_urlencoded::ser::Error),
+    /// A required environment variable is missing or not valid unicode.
     Var(VarError),
 }
 
+impl Error {
+    pub(super) fn decode(endpoint: &str, body: &[u8], error: serde_json::Error) -> Self {
+        Self::Decode {
+            body_snippet: String::from_utf8_lossy(body)
+                .chars()
+                .take(BODY_SNIPPET_LENGTH)
+                .collect(),
+            endpoint: endpoint.split('?').next().unwrap_or(endpoint).to_string(),
+            error,
+        }
+    }
+
+    /// Classifies an unsuccessful [`Response`] of the osu! API.
+    pub(super) fn from_response(response: &Response) -> Self {
+        match response.status {
+            401 => Self::Unauthorized,
+            404 => Self::NotFound,
+            429 => Self::RateLimited {
+                retry_after: response
+                    .header("Retry-After")
+                    .and_then(|seconds| seconds.trim().parse().ok())
+                    .map(Duration::from_secs),
+            },
+            500..=599 => Self::Server(response.status),
+            status => Self::Status(status),
+        }
+    }
+
+    /// Returns whether the request that produced this error may succeed if performed again.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::Server(_) => true,
+            Self::Reqwest(reqwest) => !reqwest.is_builder() && !reqwest.is_decode(),
+            _ => false,
+        }
+    }
+}
+
 impl error::Error for Error {}
 
 impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Acquire(acquire) => acquire.fmt(formatter),
+            Self::Decode {
+                body_snippet,
+                endpoint,
+                error,
+            } => write!(
+                formatter,
+                "osu! API endpoint {endpoint} returned an unexpected response ({error}): {body_snippet}"
+            ),
+            Self::NotFound => write!(formatter, "osu! API resource not found"),
             Self::ParseInt(parse_int) => parse_int.fmt(formatter),
+            #[cfg(feature = "redis")]
+            Self::Redis(redis) => redis.fmt(formatter),
+            Self::RateLimited {
+                retry_after: Some(retry_after),
+            } => write!(
+                formatter,
+                "osu! API rate limit exceeded, retry after {} seconds",
+                retry_after.as_secs()
+            ),
+            Self::RateLimited { retry_after: None } => {
+                write!(formatter, "osu! API rate limit exceeded")
+            }
+            Self::RateLimiterMismatch(configuration) => write!(
+                formatter,
+                "osu! API rate limiter is shared with a different burst and rate ({configuration})"
+            ),
             Self::Reqwest(reqwest) => reqwest.fmt(formatter),
+            Self::RetriesExhausted { attempts, error } => write!(
+                formatter,
+                "osu! API request failed after {attempts} attempts: {error}"
+            ),
+            Self::SerdeJson(serde_json) => serde_json.fmt(formatter),
+            Self::Server(status) => write!(formatter, "osu! API failed with status {status}"),
+            Self::Status(status) => write!(formatter, "osu! API responded with status {status}"),
+            Self::Unauthorized => write!(formatter, "osu! API rejected the credentials"),
+            Self::UrlEncoded(url_encoded) => url_encoded.fmt(formatter),
             Self::Var(var) => var.fmt(formatter),
         }
     }
 }
 
-impl From<AcquireError> for Error {
-    fn from(error: AcquireError) -> Self {
-        Self::Acquire(error)
-    }
-}
-
 impl From<ParseIntError> for Error {
     fn from(error: ParseIntError) -> Self {
         Self::ParseInt(error)
     }
 }
 
+#[cfg(feature = "redis")]
+impl From<redis::RedisError> for Error {
+    fn from(error: redis::RedisError) -> Self {
+        Self::Redis(error)
+    }
+}
+
 impl From<reqwest::Error> for Error {
     fn from(error: reqwest::Error) -> Self {
         Self::Reqwest(error)
     }
 }
 
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::SerdeJson(error)
+    }
+}
+
+impl From<serde_urlencoded::ser::Error> for Error {
+    fn from(error: serde_urlencoded::ser::Error) -> Self {
+        Self::UrlEncoded(error)
+    }
+}
+
 impl From<VarError> for Error {
     fn from(error: VarError) -> Self {
         Self::Var(error)
@@ -1,22 +1,29 @@
+pub mod cache;
 mod error;
 pub mod models;
+pub mod rate_limiter;
+pub mod transport;
 
-use self::models::{
-    Beatmap, Beatmaps, Beatmapset, Credentials, Headers, LeaderboardBeatmapsets, Token, User,
+use self::{
+    cache::{CacheTtls, Endpoint, ResponseCache},
+    models::{
+        AuthorizationCode, AuthorizationRequest, Beatmap, BeatmapAttributes,
+        BeatmapAttributesQuery, BeatmapDifficultyAttributes, BeatmapUserScore, BeatmapUserScores,
+        Beatmaps, Beatmapset, Configuration, Credentials, Headers, Mode, RetryPolicy, Score,
+        ScoreType, SearchPage, SearchQuery, Token, User, Users,
+    },
+    rate_limiter::{RateLimiter, RateLimiterMetrics, TokenBucket},
+    transport::{Request, ReqwestTransport, Response, Transport},
 };
-use base64::engine::{general_purpose::STANDARD, Engine as _};
 use chrono::Utc;
-use std::{
-    collections::VecDeque,
-    env,
-    fmt::Display,
-    sync::Arc,
-    time::{Duration, Instant},
-};
-use tokio::{
-    sync::{Mutex, Semaphore},
-    time::sleep,
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt, TryStreamExt,
 };
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use std::{fmt::Display, sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
 
 pub type Error = error::Error;
 
@@ -24,66 +31,211 @@ pub const IDS_LIMIT: usize = 50;
 
 #[derive(Clone)]
 pub struct Client {
-    client: reqwest::Client,
+    base_url: String,
+    cache: Option<Arc<dyn ResponseCache>>,
+    cache_ttls: CacheTtls,
     credentials: Credentials,
     headers: Arc<Mutex<Headers>>,
-    rate_limiter: Arc<Semaphore>,
-    request_queue: Arc<Mutex<VecDeque<Instant>>>,
+    rate_limiter: Arc<dyn RateLimiter>,
+    retry_policy: RetryPolicy,
+    token_url: String,
+    transport: Arc<dyn Transport>,
 }
 
 impl Client {
-    async fn drop_permit(&self) -> Result<(), Error> {
-        self.request_queue.lock().await.push_front(Instant::now());
-        self.rate_limiter.add_permits(1);
-        Ok(())
+    /// Exchanges the `code` received by the `redirect_uri` of the OAuth application for a token
+    /// that acts on behalf of the user who authorized it.
+    pub async fn authorize(&self, code: &str, redirect_uri: &str) -> Result<Token, Error> {
+        let response = self
+            .transport
+            .send(
+                Request::new(Method::POST, self.token_url.clone())
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::to_vec(&AuthorizationCode {
+                        client_id: &self.credentials.client_id,
+                        client_secret: &self.credentials.client_secret,
+                        code,
+                        grant_type: "authorization_code",
+                        redirect_uri,
+                    })?),
+            )
+            .await?;
+        if !(200..300).contains(&response.status) {
+            return Err(Error::from_response(&response));
+        }
+        Self::decode(&self.token_url, response)
     }
 
-    fn extract_beatmaps(beatmaps: &mut Vec<Beatmap>, beatmapset: Beatmapset) {
-        for beatmap in beatmapset.beatmaps.unwrap() {
-            if !beatmap.is_standard() {
-                continue;
+    /// Returns the URL of the osu! page where users authorize the OAuth application to identify
+    /// them, which redirects them back to the `redirect_uri` along with the `state`.
+    pub fn authorization_url(&self, redirect_uri: &str, state: &str) -> Result<String, Error> {
+        Ok(format!(
+            "{}/oauth/authorize?{}",
+            self.base_url,
+            serde_urlencoded::to_string(AuthorizationRequest {
+                client_id: &self.credentials.client_id,
+                redirect_uri,
+                response_type: "code",
+                scope: "identify",
+                state,
+            })?
+        ))
+    }
+
+    fn decode<T: DeserializeOwned>(endpoint: &str, response: Response) -> Result<T, Error> {
+        serde_json::from_slice::<T>(&response.body)
+            .map_err(|error| Error::decode(endpoint, &response.body, error))
+    }
+
+    pub fn from_environment(requests_per_minute: usize) -> Result<Self, Error> {
+        Ok(Self::new(
+            Configuration::from_environment(requests_per_minute)?,
+            ReqwestTransport::default(),
+        ))
+    }
+
+    /// Creates a new [`Client`] from environment variables, sharing its rate limiter in Redis
+    /// under the `OSU_API_RATE_LIMITER_KEY` environment variable if it is set.
+    ///
+    /// A shared rate limiter requires `OSU_API_REQUESTS_PER_MINUTE` to be set, so that every
+    /// process sharing it is configured with the same rate instead of its own default.
+    #[cfg(feature = "redis")]
+    pub fn from_environment_with_shared_rate_limiter(
+        connection: redis::aio::ConnectionManager,
+        requests_per_minute: usize,
+    ) -> Result<Self, Error> {
+        let configuration = Configuration::from_environment(requests_per_minute)?;
+        let (burst, requests_per_minute) = (configuration.burst, configuration.requests_per_minute);
+        let client = Self::new(configuration, ReqwestTransport::default());
+        Ok(match std::env::var("OSU_API_RATE_LIMITER_KEY") {
+            Ok(key) if !key.is_empty() => {
+                std::env::var("OSU_API_REQUESTS_PER_MINUTE")?;
+                client.with_rate_limiter(rate_limiter::RedisTokenBucket::new(
+                    burst,
+                    connection,
+                    key,
+                    requests_per_minute.clamp(1, 1000),
+                ))
             }
-            beatmaps.push(Beatmap {
-                id: beatmap.id,
-                beatmapset: Some(Beatmapset {
-                    id: beatmapset.id,
-                    beatmaps: None,
-                    favourite_count: beatmapset.favourite_count,
-                    play_count: beatmapset.play_count,
-                    ranked: beatmapset.ranked,
-                    ranked_date: beatmapset.ranked_date,
-                    title: beatmapset.title.clone(),
-                }),
-                beatmapset_id: beatmap.beatmapset_id,
-                checksum: beatmap.checksum,
-                total_length: beatmap.total_length,
-                last_updated: beatmap.last_updated,
-                mode: beatmap.mode,
-                ranked: beatmap.ranked,
-                version: beatmap.version,
-            });
+            _ => client,
+        })
+    }
+
+    async fn get(&mut self, path: &str) -> Result<Response, Error> {
+        let url = format!("{}/api/v2/{path}", self.base_url);
+        self.perform(Request::new(Method::GET, url)).await
+    }
+
+    /// Performs a GET request like [`Client::get`], going through the cache if the responses of
+    /// the `endpoint` are cached.
+    async fn get_cached(&mut self, endpoint: Endpoint, path: &str) -> Result<Response, Error> {
+        let (Some(cache), Some(ttl)) = (self.cache.clone(), self.cache_ttls.get(endpoint)) else {
+            return self.get(path).await;
+        };
+        if let Ok(Some(body)) = cache.get(path).await {
+            return Ok(Response::new(200, body));
         }
+        let response = self.get(path).await?;
+        cache.set(path, &response.body, ttl).await.ok();
+        Ok(response)
     }
 
-    pub fn from_environment(requests_per_minute: usize) -> Result<Self, Error> {
-        let requests_rate = requests_per_minute.clamp(1, 1000);
-        let mut request_queue = VecDeque::<Instant>::with_capacity(requests_rate);
-        let starting_instant = Instant::now().checked_sub(Duration::from_secs(60)).unwrap();
-        for _ in 0..requests_rate {
-            request_queue.push_front(starting_instant);
+    async fn get_json<T: DeserializeOwned>(
+        &mut self,
+        endpoint: Endpoint,
+        path: &str,
+    ) -> Result<T, Error> {
+        let response = self.get_cached(endpoint, path).await?;
+        Self::decode(path, response)
+    }
+
+    async fn invalidate_token(&self, token: &str) {
+        let mut headers = self.headers.lock().await;
+        if headers.token == token {
+            headers.expiration = Utc::now();
         }
-        Ok(Self {
-            client: reqwest::Client::new(),
+    }
+
+    pub fn new(configuration: Configuration, transport: impl Transport + 'static) -> Self {
+        Self {
+            base_url: configuration.base_url,
+            cache: None,
+            cache_ttls: CacheTtls::default(),
             credentials: Credentials {
-                client_id: env::var("OSU_API_CLIENT_ID")?,
-                client_secret: env::var("OSU_API_CLIENT_SECRET")?,
+                client_id: configuration.client_id,
+                client_secret: configuration.client_secret,
                 grant_type: String::from("client_credentials"),
                 scope: String::from("public"),
             },
             headers: Arc::new(Mutex::new(Headers::new(String::default(), Utc::now()))),
-            rate_limiter: Arc::new(Semaphore::new(requests_rate)),
-            request_queue: Arc::new(Mutex::new(request_queue)),
-        })
+            rate_limiter: Arc::new(TokenBucket::new(
+                configuration.burst,
+                configuration.requests_per_minute.clamp(1, 1000),
+            )),
+            retry_policy: configuration.retry_policy,
+            token_url: configuration.token_url,
+            transport: Arc::new(transport),
+        }
+    }
+
+    /// Performs a request to the osu! API, retrying it according to the retry policy.
+    ///
+    /// A rejected token is retried only once with a new one, and rejected credentials never are.
+    async fn perform(&mut self, request: Request) -> Result<Response, Error> {
+        let mut attempts = 0;
+        let mut is_reauthorized = false;
+        loop {
+            attempts += 1;
+            let error = match self.refresh_token().await {
+                Err(Error::Unauthorized) => return Err(Error::Unauthorized),
+                Err(error) => error,
+                Ok(()) => match self.send(request.clone()).await {
+                    Ok(response) if (200..300).contains(&response.status) => return Ok(response),
+                    Ok(response) => Error::from_response(&response),
+                    Err(error) => error,
+                },
+            };
+            let delay = match error {
+                Error::Unauthorized
+                    if !is_reauthorized && attempts <= self.retry_policy.max_retries =>
+                {
+                    is_reauthorized = true;
+                    Duration::ZERO
+                }
+                error => self.retry_delay(error, attempts)?,
+            };
+            sleep(delay).await;
+        }
+    }
+
+    /// Returns the delay before retrying a request that failed with `error` on its attempt number
+    /// `attempts`, or the error to return if it must not be retried.
+    ///
+    /// Rate limited requests are not retried if osu! asks to wait longer than the maximum delay of
+    /// the retry policy.
+    fn retry_delay(&self, error: Error, attempts: u32) -> Result<Duration, Error> {
+        let delay = match error {
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+            } if retry_after > self.retry_policy.max_delay => return Err(error),
+            Error::RateLimited {
+                retry_after: Some(retry_after),
+            } => retry_after,
+            _ if error.is_retryable() => self.retry_policy.backoff(attempts),
+            _ => return Err(error),
+        };
+        if attempts > self.retry_policy.max_retries {
+            return Err(Error::RetriesExhausted {
+                attempts,
+                error: Box::new(error),
+            });
+        }
+        Ok(delay)
+    }
+
+    /// Returns a snapshot of the state of the rate limiter shared by the clones of this [`Client`].
+    pub fn rate_limiter_metrics(&self) -> RateLimiterMetrics {
+        self.rate_limiter.metrics()
     }
 
     async fn refresh_token(&mut self) -> Result<(), Error> {
@@ -92,12 +244,17 @@ impl Client {
             return Ok(());
         }
         let response = self
-            .client
-            .post("https://osu.ppy.sh/oauth/token")
-            .json(&self.credentials)
-            .send()
+            .transport
+            .send(
+                Request::new(Method::POST, self.token_url.clone())
+                    .header("Content-Type", "application/json")
+                    .body(serde_json::to_vec(&self.credentials)?),
+            )
             .await?;
-        let token = response.json::<Token>().await?;
+        if !(200..300).contains(&response.status) {
+            return Err(Error::from_response(&response));
+        }
+        let token = Self::decode::<Token>(&self.token_url, response)?;
         *headers = Headers::new(
             format!("{} {}", token.token_type, token.access_token),
             Utc::now() + chrono::Duration::seconds((token.expires_in - 900) as i64),
@@ -105,74 +262,124 @@ impl Client {
         Ok(())
     }
 
-    pub async fn retrieve_beatmap(&mut self, id: i32) -> Result<Option<Beatmap>, Error> {
-        self.request_permit().await?;
-        let token = self.headers.lock().await.token.clone();
-        let response = self
-            .client
-            .get(format!("https://osu.ppy.sh/api/v2/beatmaps/{id}"))
-            .header("Authorization", token)
-            .send()
-            .await;
-        self.drop_permit().await?;
-        let beatmap_response = response?;
-        if beatmap_response.status() == 404 {
-            return Ok(None);
-        }
-        let beatmap = beatmap_response.json::<Beatmap>().await?;
-        if beatmap.is_standard() {
-            Ok(Some(beatmap))
-        } else {
-            Ok(None)
+    /// Retrieves the user who authorized the `token` through [`Client::authorize`], retrying the
+    /// request according to the retry policy.
+    pub async fn retrieve_authorized_user(&self, token: &Token) -> Result<User, Error> {
+        let path = "me/osu";
+        let request = Request::new(Method::GET, format!("{}/api/v2/{path}", self.base_url)).header(
+            "Authorization",
+            format!("{} {}", token.token_type, token.access_token),
+        );
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            self.rate_limiter.acquire().await?;
+            let error = match self.transport.send(request.clone()).await {
+                Ok(response) if (200..300).contains(&response.status) => {
+                    return Self::decode(path, response)
+                }
+                Ok(response) => Error::from_response(&response),
+                Err(error) => error,
+            };
+            sleep(self.retry_delay(error, attempts)?).await;
         }
     }
 
+    /// Retrieves the difficulty attributes of a beatmap computed by osu! with the specified
+    /// `mods` bitflags, or `None` if the beatmap does not exist.
+    pub async fn retrieve_beatmap_attributes(
+        &mut self,
+        id: i32,
+        mods: u32,
+    ) -> Result<Option<BeatmapAttributes>, Error> {
+        let path = format!("beatmaps/{id}/attributes");
+        let request = Request::new(Method::POST, format!("{}/api/v2/{path}", self.base_url))
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&BeatmapAttributesQuery {
+                mods,
+                ruleset: Mode::Osu.as_str(),
+            })?);
+        let Some(response) = optional(self.perform(request).await)? else {
+            return Ok(None);
+        };
+        Ok(Some(
+            Self::decode::<BeatmapDifficultyAttributes>(&path, response)?.attributes,
+        ))
+    }
+
+    pub async fn retrieve_beatmap(&mut self, id: i32) -> Result<Option<Beatmap>, Error> {
+        let beatmap = optional(
+            self.get_json::<Beatmap>(Endpoint::Beatmap, &format!("beatmaps/{id}"))
+                .await,
+        )?;
+        Ok(beatmap.filter(|beatmap| beatmap.is_standard()))
+    }
+
+    /// Retrieves the beatmaps with the specified `ids`, skipping the ones that do not exist and
+    /// caching each beatmap on its own under the path of a batch with only its id, so it expires
+    /// with the TTL of [`Endpoint::Beatmaps`] instead of the one of [`Endpoint::Beatmap`].
     pub async fn retrieve_beatmaps(&mut self, ids: &[i32]) -> Result<Vec<Beatmap>, Error> {
+        let cache = self
+            .cache
+            .clone()
+            .zip(self.cache_ttls.get(Endpoint::Beatmaps));
+        let mut beatmaps = Vec::with_capacity(ids.len());
         let mut query_string = Vec::with_capacity(ids.len());
         for id in ids {
+            let key = format!("beatmaps?ids[]={id}");
+            if let Some((cache, _)) = &cache {
+                if let Ok(Some(body)) = cache.get(&key).await {
+                    if let Ok(beatmap) = serde_json::from_slice::<Beatmap>(&body) {
+                        beatmaps.push(beatmap);
+                        continue;
+                    }
+                }
+            }
             query_string.push(format!("ids[]={id}"))
         }
-        self.request_permit().await?;
-        let token = self.headers.lock().await.token.clone();
-        let response = self
-            .client
-            .get(format!(
-                "https://osu.ppy.sh/api/v2/beatmaps?{}",
-                query_string.join("&")
-            ))
-            .header("Authorization", token)
-            .send()
-            .await;
-        self.drop_permit().await?;
-        let beatmaps = response?.json::<Beatmaps>().await?.beatmaps;
-        Ok(beatmaps
-            .into_iter()
-            .filter(|beatmap| beatmap.is_standard())
-            .collect())
+        if !query_string.is_empty() {
+            let path = format!("beatmaps?{}", query_string.join("&"));
+            let response = self.get(&path).await?;
+            let Some((cache, ttl)) = &cache else {
+                beatmaps.extend(Self::decode::<Beatmaps>(&path, response)?.beatmaps);
+                return Ok(Self::standard_beatmaps(beatmaps));
+            };
+            for value in Self::decode::<Beatmaps<serde_json::Value>>(&path, response)?.beatmaps {
+                let body = value.to_string();
+                let beatmap = serde_json::from_value::<Beatmap>(value)
+                    .map_err(|error| Error::decode(&path, body.as_bytes(), error))?;
+                cache
+                    .set(
+                        &format!("beatmaps?ids[]={}", beatmap.id),
+                        body.as_bytes(),
+                        *ttl,
+                    )
+                    .await
+                    .ok();
+                beatmaps.push(beatmap);
+            }
+        }
+        Ok(Self::standard_beatmaps(beatmaps))
     }
 
     pub async fn retrieve_beatmapset(
         &mut self,
         id: impl Display,
     ) -> Result<Option<Beatmapset>, Error> {
-        self.request_permit().await?;
-        let token = self.headers.lock().await.token.clone();
-        let response = self
-            .client
-            .get(format!("https://osu.ppy.sh/api/v2/beatmapsets/{id}"))
-            .header("Authorization", token)
-            .send()
-            .await;
-        self.drop_permit().await?;
-        let beatmapset_response = response?;
-        if beatmapset_response.status() == 404 {
+        let path = format!("beatmapsets/{id}");
+        let Some(response) = optional(self.get_cached(Endpoint::Beatmapset, &path).await)? else {
             return Ok(None);
-        }
-        let mut beatmapset = beatmapset_response.json::<Beatmapset>().await?;
+        };
+        let mut beatmapset = Self::decode::<Beatmapset>(&path, response.clone())?;
+        let Some(beatmaps) = beatmapset.beatmaps.take() else {
+            return Err(Error::decode(
+                &path,
+                &response.body,
+                serde::de::Error::missing_field("beatmaps"),
+            ));
+        };
         beatmapset.beatmaps = Some(
-            beatmapset
-                .beatmaps
-                .unwrap()
+            beatmaps
                 .into_iter()
                 .filter(|beatmap| beatmap.is_standard())
                 .collect(),
@@ -180,99 +387,245 @@ impl Client {
         Ok(Some(beatmapset))
     }
 
-    pub async fn retrieve_leaderboard_beatmaps(
-        &mut self,
-        approved_date: i64,
-        id: i32,
-        limit_date: i64,
-    ) -> Result<(Vec<Beatmap>, i64, i32), Error> {
-        if approved_date >= limit_date {
-            return Ok((Vec::new(), approved_date, id));
-        }
-        let cursor_string = STANDARD.encode(format!(
-            r#"{{"approved_date":"{approved_date}","id":"{id}"}}"#
-        ));
-        self.request_permit().await?;
-        let token = self.headers.lock().await.token.clone();
-        let response = self
-            .client
-            .get(format!("https://osu.ppy.sh/api/v2/beatmapsets/search?m=0&nsfw=true&sort=ranked_asc&cursor_string={cursor_string}"))
-            .header("Authorization", token).send().await;
-        self.drop_permit().await?;
-        let ranked_beatmaps = response?.json::<LeaderboardBeatmapsets>().await?;
-        let mut beatmaps = Vec::<Beatmap>::new();
-        let (mut new_approved_date, mut new_id) = if let Some(new_cursor) = ranked_beatmaps.cursor {
-            (new_cursor.approved_date.parse()?, new_cursor.id.parse()?)
-        } else {
-            (limit_date, id)
-        };
-        for beatmapset in ranked_beatmaps.beatmapsets {
-            let ranked_date = beatmapset.ranked_date.unwrap();
-            let approved_date = ranked_date.timestamp_millis();
-            if approved_date <= limit_date {
-                (new_approved_date, new_id) = (approved_date, beatmapset.id);
-                Self::extract_beatmaps(&mut beatmaps, beatmapset);
-            }
-        }
-        Ok((beatmaps, new_approved_date, new_id))
-    }
-
     pub async fn retrieve_user(
         &mut self,
         user: impl Display,
         is_username: bool,
     ) -> Result<Option<User>, Error> {
-        self.request_permit().await?;
+        optional(
+            self.get_json::<User>(
+                Endpoint::User,
+                &format!(
+                    "users/{user}/osu?key={}",
+                    if is_username { "username" } else { "id" }
+                ),
+            )
+            .await,
+        )
+    }
+
+    /// Retrieves the best score of a user on a beatmap, or `None` if the user or the beatmap do not
+    /// exist or the user has no scores on it.
+    pub async fn retrieve_user_beatmap_score(
+        &mut self,
+        beatmap_id: i32,
+        mode: Mode,
+        user_id: i32,
+    ) -> Result<Option<BeatmapUserScore>, Error> {
+        optional(
+            self.get_json::<BeatmapUserScore>(
+                Endpoint::Scores,
+                &format!(
+                    "beatmaps/{beatmap_id}/scores/users/{user_id}?mode={}",
+                    mode.as_str()
+                ),
+            )
+            .await,
+        )
+    }
+
+    /// Retrieves every score of a user on a beatmap, which is empty if the user or the beatmap do
+    /// not exist.
+    pub async fn retrieve_user_beatmap_scores(
+        &mut self,
+        beatmap_id: i32,
+        mode: Mode,
+        user_id: i32,
+    ) -> Result<Vec<Score>, Error> {
+        let scores = optional(
+            self.get_json::<BeatmapUserScores>(
+                Endpoint::Scores,
+                &format!(
+                    "beatmaps/{beatmap_id}/scores/users/{user_id}/all?mode={}",
+                    mode.as_str()
+                ),
+            )
+            .await,
+        )?;
+        Ok(scores.map(|scores| scores.scores).unwrap_or_default())
+    }
+
+    /// Retrieves up to `limit` scores of a list of scores of a user, skipping the first `offset`,
+    /// which is empty if the user does not exist.
+    pub async fn retrieve_user_scores(
+        &mut self,
+        limit: usize,
+        mode: Mode,
+        offset: usize,
+        score_type: ScoreType,
+        user_id: i32,
+    ) -> Result<Vec<Score>, Error> {
+        let scores = optional(
+            self.get_json::<Vec<Score>>(
+                Endpoint::Scores,
+                &format!(
+                    "users/{user_id}/scores/{}?mode={}&limit={limit}&offset={offset}",
+                    score_type.as_str(),
+                    mode.as_str()
+                ),
+            )
+            .await,
+        )?;
+        Ok(scores.unwrap_or_default())
+    }
+
+    /// Retrieves the users with the specified `ids`, skipping the ones that do not exist.
+    pub async fn retrieve_users(&mut self, ids: &[i32]) -> Result<Vec<User>, Error> {
+        let mut query_string = Vec::with_capacity(ids.len());
+        for id in ids {
+            query_string.push(format!("ids[]={id}"))
+        }
+        Ok(self
+            .get_json::<Users>(
+                Endpoint::Users,
+                &format!("users?{}", query_string.join("&")),
+            )
+            .await?
+            .users)
+    }
+
+    fn standard_beatmaps(beatmaps: Vec<Beatmap>) -> Vec<Beatmap> {
+        beatmaps
+            .into_iter()
+            .filter(|beatmap| beatmap.is_standard())
+            .collect()
+    }
+
+    /// Searches the beatmapsets matching the `query`, yielding each page of results along with the
+    /// cursor of the next one.
+    pub fn search_beatmapset_pages(
+        &self,
+        query: SearchQuery,
+    ) -> BoxStream<'static, Result<SearchPage, Error>> {
+        stream::try_unfold(
+            (self.clone(), Some(query)),
+            |(mut client, query)| async move {
+                let Some(query) = query else {
+                    return Ok(None);
+                };
+                let page = client
+                    .get_json::<SearchPage>(
+                        Endpoint::Search,
+                        &format!(
+                            "beatmapsets/search?{}",
+                            serde_urlencoded::to_string(&query)?
+                        ),
+                    )
+                    .await?;
+                let next_query = match &page.cursor_string {
+                    Some(cursor_string) if !page.beatmapsets.is_empty() => {
+                        Some(query.cursor_string(Some(cursor_string.clone())))
+                    }
+                    _ => None,
+                };
+                Ok(Some((page, (client, next_query))))
+            },
+        )
+        .boxed()
+    }
+
+    /// Searches the beatmapsets matching the `query`, following every page of results.
+    pub fn search_beatmapsets(
+        &self,
+        query: SearchQuery,
+    ) -> BoxStream<'static, Result<Beatmapset, Error>> {
+        self.search_beatmapset_pages(query)
+            .map_ok(|page| stream::iter(page.beatmapsets.into_iter().map(Ok)))
+            .try_flatten()
+            .boxed()
+    }
+
+    async fn send(&mut self, request: Request) -> Result<Response, Error> {
         let token = self.headers.lock().await.token.clone();
+        self.rate_limiter.acquire().await?;
         let response = self
-            .client
-            .get(format!(
-                "https://osu.ppy.sh/api/v2/users/{user}?key={}",
-                if is_username { "username" } else { "id" }
-            ))
-            .header("Authorization", token)
-            .send()
-            .await;
-        self.drop_permit().await?;
-        let user = response?;
-        if user.status() == 404 {
-            return Ok(None);
+            .transport
+            .send(request.header("Authorization", token.clone()))
+            .await?;
+        if response.status == 401 {
+            self.invalidate_token(&token).await;
         }
-        Ok(Some(user.json::<User>().await?))
+        Ok(response)
     }
 
-    async fn request_permit(&mut self) -> Result<(), Error> {
-        self.rate_limiter.clone().acquire_owned().await?.forget();
-        self.refresh_token().await?;
-        let seconds_elapsed = self
-            .request_queue
-            .lock()
-            .await
-            .pop_back()
-            .unwrap()
-            .elapsed()
-            .as_secs_f64();
-        if seconds_elapsed < 60.0 {
-            sleep(Duration::from_secs_f64(60.0 - seconds_elapsed)).await;
-        }
-        Ok(())
+    /// Caches the responses of this [`Client`] in `cache` for the TTLs of their endpoints.
+    pub fn with_cache(mut self, cache: impl ResponseCache + 'static, ttls: CacheTtls) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self.cache_ttls = ttls;
+        self
+    }
+
+    /// Replaces the rate limiter of this [`Client`], e.g., with one shared by several processes.
+    pub fn with_rate_limiter(mut self, rate_limiter: impl RateLimiter + 'static) -> Self {
+        self.rate_limiter = Arc::new(rate_limiter);
+        self
+    }
+}
+
+/// Maps [`Error::NotFound`] to `None`, for the resources that may not exist.
+fn optional<T>(result: Result<T, Error>) -> Result<Option<T>, Error> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(Error::NotFound) => Ok(None),
+        Err(error) => Err(error),
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Client;
+    use super::{
+        cache::{CacheTtls, Endpoint, MemoryCache},
+        models::{
+            Configuration, Genre, Language, Mode, RankedStatus, ScoreType, SearchQuery, SearchSort,
+            SearchStatus,
+        },
+        rate_limiter::TokenBucket,
+        transport::{Response, StubTransport},
+        Client, Error as OsuApiError,
+    };
     use chrono::{DateTime, Utc};
     use dotenv::dotenv;
-    use std::{error::Error, str::FromStr, time::Instant};
-    use tokio::spawn;
+    use futures::TryStreamExt;
+    use reqwest::Method;
+    use std::{
+        error::Error,
+        str::FromStr,
+        time::{Duration, Instant},
+    };
+    use tokio::{fs, spawn, time};
+
+    const STUB_URL: &str = "http://osu.test";
 
     fn setup_test(requests_per_minute: usize) -> Result<Client, Box<dyn Error>> {
         dotenv().ok();
         Ok(Client::from_environment(requests_per_minute)?)
     }
 
+    async fn setup_stub_test(
+        requests_per_minute: usize,
+        transport: &StubTransport,
+    ) -> Result<Client, Box<dyn Error>> {
+        transport.add_response(
+            Method::POST,
+            format!("{STUB_URL}/oauth/token"),
+            Response::new(200, fixture("token").await?),
+        );
+        let mut configuration = Configuration::new(
+            STUB_URL,
+            String::from("client_id"),
+            String::from("client_secret"),
+            requests_per_minute,
+        );
+        configuration.burst = requests_per_minute;
+        Ok(Client::new(configuration, transport.clone()))
+    }
+
+    async fn fixture(name: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(fs::read(format!("./test_files/osu_api/{name}.json")).await?)
+    }
+
     #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
     async fn test_retrieve_beatmap() -> Result<(), Box<dyn Error>> {
         let mut client = setup_test(3)?;
         let beatmap = client.retrieve_beatmap(555797).await?.unwrap();
@@ -284,7 +637,7 @@ mod tests {
             DateTime::<Utc>::from_str("2015-03-17T22:31:07+00:00")?
         );
         assert_eq!(beatmap.mode, "osu");
-        assert_eq!(beatmap.ranked, 1);
+        assert_eq!(beatmap.ranked, RankedStatus::Ranked);
         assert_eq!(beatmap.total_length, 194);
         assert_eq!(beatmap.version, "Time Freeze");
         let beatmapset = beatmap.beatmapset.unwrap();
@@ -303,6 +656,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
     async fn test_retrieve_beatmaps() -> Result<(), Box<dyn Error>> {
         let mut client = setup_test(2)?;
         let beatmaps = client.retrieve_beatmaps(&[555797]).await?;
@@ -314,7 +668,7 @@ mod tests {
             DateTime::<Utc>::from_str("2015-03-17T22:31:07+00:00")?
         );
         assert_eq!(beatmaps[0].mode, "osu");
-        assert_eq!(beatmaps[0].ranked, 1);
+        assert_eq!(beatmaps[0].ranked, RankedStatus::Ranked);
         assert_eq!(beatmaps[0].total_length, 194);
         assert_eq!(beatmaps[0].version, "Time Freeze");
         let beatmapset = beatmaps[0].beatmapset.as_ref().unwrap();
@@ -332,6 +686,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
     async fn test_retrieve_beatmapset() -> Result<(), Box<dyn Error>> {
         let mut client = setup_test(3)?;
         let beatmapset = client.retrieve_beatmapset(158023).await?.unwrap();
@@ -356,7 +711,7 @@ mod tests {
             DateTime::<Utc>::from_str("2015-03-17T22:31:07+00:00")?
         );
         assert_eq!(beatmap.mode, "osu");
-        assert_eq!(beatmap.ranked, 1);
+        assert_eq!(beatmap.ranked, RankedStatus::Ranked);
         assert_eq!(beatmap.version, "Time Freeze");
         assert!(client.retrieve_beatmapset(121252).await?.is_none());
         assert_eq!(
@@ -373,12 +728,49 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_retrieve_leaderboard_beatmaps() -> Result<(), Box<dyn Error>> {
-        let mut client = setup_test(2)?;
-        let (beatmaps, approved_date, id) =
-            client.retrieve_leaderboard_beatmaps(0, 0, i64::MAX).await?;
-        assert_eq!(beatmaps.len(), 70);
-        let beatmap = &beatmaps[0];
+    #[ignore = "requires osu! API credentials"]
+    async fn test_retrieve_user_scores() -> Result<(), Box<dyn Error>> {
+        let mut client = setup_test(3)?;
+        let scores = client
+            .retrieve_user_scores(5, Mode::Osu, 0, ScoreType::Best, 6484647)
+            .await?;
+        assert_eq!(scores.len(), 5);
+        assert!(scores.iter().all(|score| score.user_id == 6484647));
+        let beatmap = scores[0].beatmap.as_ref().unwrap();
+        let beatmap_score = client
+            .retrieve_user_beatmap_score(beatmap.id, Mode::Osu, 6484647)
+            .await?
+            .unwrap();
+        assert_eq!(beatmap_score.score.user_id, 6484647);
+        assert!(client
+            .retrieve_user_scores(5, Mode::Osu, 0, ScoreType::Best, 0)
+            .await?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
+    async fn test_search_beatmapsets() -> Result<(), Box<dyn Error>> {
+        let client = setup_test(2)?;
+        let query = SearchQuery::default()
+            .mode(Mode::Osu)
+            .nsfw(true)
+            .sort(SearchSort::RankedAsc);
+        let mut pages = client.search_beatmapset_pages(query);
+        let page = pages.try_next().await?.unwrap();
+        assert_eq!(page.beatmapsets.len(), 50);
+        assert!(page.cursor_string.is_some());
+        let beatmapset = page.beatmapsets.into_iter().next().unwrap();
+        assert!(beatmapset.favourite_count > 1100);
+        assert_eq!(beatmapset.id, 1);
+        assert!(beatmapset.play_count > 580000);
+        assert_eq!(
+            beatmapset.ranked_date.unwrap(),
+            DateTime::<Utc>::from_str("2007-10-06T17:46:31+00:00")?
+        );
+        assert_eq!(beatmapset.title, "DISCO PRINCE");
+        let beatmap = &beatmapset.into_standard_beatmaps()[0];
         assert_eq!(beatmap.beatmapset_id, 1);
         assert_eq!(beatmap.checksum, "a5b99395a42bd55bc5eb1d2411cbdf8b");
         assert_eq!(beatmap.total_length, 142);
@@ -388,30 +780,20 @@ mod tests {
             DateTime::<Utc>::from_str("2014-05-18T17:16:42+00:00")?
         );
         assert_eq!(beatmap.mode, "osu");
-        assert_eq!(beatmap.ranked, 1);
+        assert_eq!(beatmap.ranked, RankedStatus::Ranked);
         assert_eq!(beatmap.version, "Normal");
-        let beatmapset = beatmap.beatmapset.as_ref().unwrap();
-        assert!(beatmapset.beatmaps.is_none());
-        assert!(beatmapset.favourite_count > 1100);
-        assert_eq!(beatmapset.id, 1);
-        assert!(beatmapset.play_count > 580000);
-        assert_eq!(
-            beatmapset.ranked_date.unwrap(),
-            DateTime::<Utc>::from_str("2007-10-06T17:46:31+00:00")?
+        assert!(beatmap.beatmapset.as_ref().unwrap().beatmaps.is_none());
+        let mut beatmapsets = client.search_beatmapsets(
+            SearchQuery::default()
+                .status(SearchStatus::Ranked)
+                .text("DISCO PRINCE"),
         );
-        assert_eq!(beatmapset.title, "DISCO PRINCE");
-        assert_eq!(approved_date, 1192064884000);
-        assert_eq!(id, 74);
-        let (empty_beatmaps, empty_approved_date, empty_id) = client
-            .retrieve_leaderboard_beatmaps(i64::MAX, i32::MAX, i64::MAX)
-            .await?;
-        assert_eq!(empty_beatmaps.len(), 0);
-        assert_eq!(empty_approved_date, i64::MAX);
-        assert_eq!(empty_id, i32::MAX);
+        assert!(beatmapsets.try_next().await?.is_some());
         Ok(())
     }
 
     #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
     async fn test_requests_per_minute() -> Result<(), Box<dyn Error>> {
         let client = setup_test(2)?;
         let mut tasks = Vec::new();
@@ -431,6 +813,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[ignore = "requires osu! API credentials"]
     async fn test_retrieve_user() -> Result<(), Box<dyn Error>> {
         let mut client = setup_test(2)?;
         assert_eq!(
@@ -453,4 +836,715 @@ mod tests {
         assert!(client.retrieve_user("Sombrax79", false).await?.is_none());
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_stub_configuration() -> Result<(), Box<dyn Error>> {
+        let configuration =
+            Configuration::new("http://localhost:8000/", String::new(), String::new(), 1);
+        assert_eq!(configuration.base_url, "http://localhost:8000");
+        assert_eq!(configuration.token_url, "http://localhost:8000/oauth/token");
+        let transport = StubTransport::default();
+        let mut client = setup_stub_test(2, &transport).await?;
+        assert!(client.retrieve_beatmap(1).await?.is_none());
+        assert!(client.retrieve_beatmap(1).await?.is_none());
+        let requests = transport.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].method, Method::POST);
+        assert_eq!(requests[0].url, format!("{STUB_URL}/oauth/token"));
+        assert_eq!(requests[1].url, format!("{STUB_URL}/api/v2/beatmaps/1"));
+        assert_eq!(
+            requests[2].headers,
+            [(String::from("Authorization"), String::from("Bearer access"))]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_beatmap() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                200,
+                fixture("beatmap").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/132889"),
+                200,
+                fixture("beatmap_taiko").await?,
+            );
+        let mut client = setup_stub_test(3, &transport).await?;
+        let beatmap = client.retrieve_beatmap(555797).await?.unwrap();
+        assert_eq!(beatmap.beatmapset_id, 158023);
+        assert_eq!(beatmap.checksum, "a84050da9b68ca1bd8e2d1700b9c6ca5");
+        assert_eq!(beatmap.id, 555797);
+        assert_eq!(
+            beatmap.last_updated,
+            DateTime::<Utc>::from_str("2015-03-17T22:31:07+00:00")?
+        );
+        assert_eq!(beatmap.mode, "osu");
+        assert_eq!(beatmap.ranked, RankedStatus::Ranked);
+        assert_eq!(beatmap.total_length, 194);
+        assert_eq!(beatmap.version, "Time Freeze");
+        let beatmapset = beatmap.beatmapset.unwrap();
+        assert!(beatmapset.beatmaps.is_none());
+        assert_eq!(beatmapset.favourite_count, 21523);
+        assert_eq!(beatmapset.id, 158023);
+        assert_eq!(beatmapset.play_count, 47352761);
+        assert_eq!(
+            beatmapset.ranked_date.unwrap(),
+            DateTime::<Utc>::from_str("2015-03-24T22:40:14+00:00")?
+        );
+        assert_eq!(beatmapset.title, "Everything will freeze");
+        assert!(!beatmapset.artist.is_empty());
+        assert!(!beatmapset.creator.is_empty());
+        assert!(beatmapset.bpm.is_none());
+        assert!(beatmapset.genre.is_none());
+        assert!(beatmapset.covers.cover.contains("/beatmaps/158023/covers/"));
+        assert!(client.retrieve_beatmap(1).await?.is_none());
+        assert!(client.retrieve_beatmap(132889).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_beatmap_attributes() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default().with_response(
+            Method::POST,
+            format!("{STUB_URL}/api/v2/beatmaps/555797/attributes"),
+            200,
+            fixture("beatmap_attributes").await?,
+        );
+        let mut client = setup_stub_test(2, &transport).await?;
+        let attributes = client
+            .retrieve_beatmap_attributes(555797, 64)
+            .await?
+            .unwrap();
+        assert_eq!(attributes.approach_rate, Some(9.3));
+        assert_eq!(attributes.max_combo, 1205);
+        assert_eq!(attributes.star_rating, 5.53);
+        let requests = transport.requests();
+        let body: serde_json::Value =
+            serde_json::from_slice(requests[1].body.as_deref().unwrap_or_default())?;
+        assert_eq!(body["mods"], 64);
+        assert_eq!(body["ruleset"], "osu");
+        assert!(client.retrieve_beatmap_attributes(1, 0).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_beatmaps() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=555797"),
+                200,
+                fixture("beatmaps").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=1&ids[]=132889"),
+                200,
+                fixture("beatmaps_taiko").await?,
+            );
+        let mut client = setup_stub_test(2, &transport).await?;
+        let beatmaps = client.retrieve_beatmaps(&[555797]).await?;
+        assert_eq!(beatmaps.len(), 1);
+        assert_eq!(beatmaps[0].id, 555797);
+        assert_eq!(
+            beatmaps[0].beatmapset.as_ref().unwrap().title,
+            "Everything will freeze"
+        );
+        assert!(client.retrieve_beatmaps(&[1, 132889]).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_beatmapset() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default().with_response(
+            Method::GET,
+            format!("{STUB_URL}/api/v2/beatmapsets/158023"),
+            200,
+            fixture("beatmapset").await?,
+        );
+        let mut client = setup_stub_test(2, &transport).await?;
+        let beatmapset = client.retrieve_beatmapset(158023).await?.unwrap();
+        assert_eq!(beatmapset.id, 158023);
+        assert_eq!(beatmapset.title, "Everything will freeze");
+        assert!(beatmapset.bpm.is_some());
+        assert!(beatmapset.genre.is_some());
+        assert!(beatmapset.language.is_some());
+        assert!(!beatmapset.tags.is_empty());
+        let beatmaps = beatmapset.beatmaps.unwrap();
+        assert_eq!(beatmaps.len(), 2);
+        assert!(beatmaps[1].beatmapset.is_none());
+        assert_eq!(beatmaps[1].id, 555797);
+        assert_eq!(beatmaps[1].version, "Time Freeze");
+        assert!(client.retrieve_beatmapset(121252).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_users() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default().with_response(
+            Method::GET,
+            format!("{STUB_URL}/api/v2/users?ids[]=2&ids[]=6484647&ids[]=1"),
+            200,
+            fixture("users").await?,
+        );
+        let mut client = setup_stub_test(2, &transport).await?;
+        let users = client.retrieve_users(&[2, 6484647, 1]).await?;
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[0].username, "peppy");
+        assert!(users[0].previous_usernames.is_empty());
+        assert!(users[0]
+            .standard_statistics()
+            .unwrap()
+            .global_rank
+            .is_none());
+        assert_eq!(users[1].id, 6484647);
+        assert_eq!(
+            users[1].avatar_url,
+            "https://a.ppy.sh/6484647?1650000000.jpeg"
+        );
+        assert_eq!(users[1].standard_statistics().unwrap().pp, 6123.41);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_user_scores() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/users/6484647/scores/best?mode=osu&limit=2&offset=0"),
+                200,
+                fixture("user_scores").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797/scores/users/6484647?mode=osu"),
+                200,
+                fixture("beatmap_user_score").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797/scores/users/6484647/all?mode=osu"),
+                200,
+                fixture("beatmap_user_scores").await?,
+            );
+        let mut client = setup_stub_test(10, &transport).await?;
+        let scores = client
+            .retrieve_user_scores(2, Mode::Osu, 0, ScoreType::Best, 6484647)
+            .await?;
+        assert_eq!(scores.len(), 2);
+        let score = &scores[0];
+        assert_eq!(score.accuracy, 0.9823);
+        assert!(score.has_mod("DT"));
+        assert!(!score.has_mod("HR"));
+        assert_eq!(score.pp, Some(412.36));
+        assert_eq!(score.rank, "S");
+        assert_eq!(score.statistics.count_300, 1403);
+        assert_eq!(score.user_id, 6484647);
+        assert_eq!(score.beatmap.as_ref().unwrap().id, 555797);
+        assert_eq!(score.beatmapset.as_ref().unwrap().creator, "Ekoro");
+        assert!(scores[1].mods.is_empty());
+        assert!(client
+            .retrieve_user_scores(2, Mode::Osu, 0, ScoreType::Recent, 6484647)
+            .await?
+            .is_empty());
+        let beatmap_score = client
+            .retrieve_user_beatmap_score(555797, Mode::Osu, 6484647)
+            .await?
+            .unwrap();
+        assert_eq!(beatmap_score.position, 1843);
+        assert_eq!(beatmap_score.score.id, 3861424581);
+        assert!(beatmap_score.score.beatmap.is_none());
+        assert!(client
+            .retrieve_user_beatmap_score(555797, Mode::Taiko, 6484647)
+            .await?
+            .is_none());
+        let beatmap_scores = client
+            .retrieve_user_beatmap_scores(555797, Mode::Osu, 6484647)
+            .await?;
+        assert_eq!(beatmap_scores.len(), 2);
+        assert!(beatmap_scores[1].pp.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_search_beatmapsets() -> Result<(), Box<dyn Error>> {
+        let query = SearchQuery::default()
+            .mode(Mode::Osu)
+            .nsfw(true)
+            .sort(SearchSort::RankedAsc);
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmapsets/search?m=0&nsfw=true&sort=ranked_asc"),
+                200,
+                fixture("search").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!(
+                    "{STUB_URL}/api/v2/beatmapsets/search?m=0&nsfw=true&sort=ranked_asc&cursor_string={}",
+                    "eyJhcHByb3ZlZF9kYXRlIjoiMTE5MjA2NDg4NDAwMCIsImlkIjoiNzQifQ"
+                ),
+                200,
+                fixture("search_end").await?,
+            );
+        let client = setup_stub_test(5, &transport).await?;
+        let pages = client
+            .search_beatmapset_pages(query.clone())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(pages.len(), 2);
+        assert_eq!(
+            pages[0].cursor_string.as_deref(),
+            Some("eyJhcHByb3ZlZF9kYXRlIjoiMTE5MjA2NDg4NDAwMCIsImlkIjoiNzQifQ")
+        );
+        assert!(pages[1].cursor_string.is_none());
+        let beatmapsets = client
+            .search_beatmapsets(query.clone())
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(
+            beatmapsets.iter().map(|set| set.id).collect::<Vec<_>>(),
+            [1, 74, 3]
+        );
+        assert_eq!(beatmapsets[0].title, "DISCO PRINCE");
+        let beatmaps = beatmapsets[1].clone().into_standard_beatmaps();
+        assert_eq!(beatmaps.len(), 1);
+        assert_eq!(beatmaps[0].id, 243);
+        let beatmapset = beatmaps[0].beatmapset.as_ref().unwrap();
+        assert!(beatmapset.beatmaps.is_none());
+        assert_eq!(beatmapset.artist, "Sonic Boom");
+        assert_eq!(beatmapset.user_id, 26);
+        assert!(beatmapset.video);
+        let resumed = client
+            .search_beatmapsets(query.cursor_string(pages[0].cursor_string.clone()))
+            .try_collect::<Vec<_>>()
+            .await?;
+        assert_eq!(resumed.len(), 1);
+        assert_eq!(resumed[0].id, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_query() -> Result<(), Box<dyn Error>> {
+        let query = SearchQuery::default()
+            .genre(Genre::Electronic)
+            .language(Language::Japanese)
+            .status(SearchStatus::Qualified)
+            .text("freedom dive")
+            .sort(SearchSort::PlaysDesc);
+        assert_eq!(
+            serde_urlencoded::to_string(&query)?,
+            "q=freedom+dive&s=qualified&g=10&l=3&sort=plays_desc"
+        );
+        assert_eq!(serde_urlencoded::to_string(SearchQuery::default())?, "");
+        Ok(())
+    }
+
+    #[test]
+    fn test_ranked_status() -> Result<(), Box<dyn Error>> {
+        let statuses: Vec<RankedStatus> = serde_json::from_str("[-2, -1, 0, 1, 2, 3, 4]")?;
+        assert_eq!(
+            statuses,
+            [
+                RankedStatus::Graveyard,
+                RankedStatus::Wip,
+                RankedStatus::Pending,
+                RankedStatus::Ranked,
+                RankedStatus::Approved,
+                RankedStatus::Qualified,
+                RankedStatus::Loved
+            ]
+        );
+        assert!(RankedStatus::Approved.is_ranked());
+        assert!(!RankedStatus::Qualified.is_ranked());
+        assert!(serde_json::from_str::<RankedStatus>("5").is_err());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_requests_per_minute() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default().with_response(
+            Method::GET,
+            format!("{STUB_URL}/api/v2/users/6484647/osu?key=id"),
+            200,
+            fixture("user").await?,
+        );
+        let client = setup_stub_test(2, &transport)
+            .await?
+            .with_rate_limiter(TokenBucket::new(1, 2));
+        let mut tasks = Vec::new();
+        let start = time::Instant::now();
+        for _ in 0..3 {
+            let mut clone = client.clone();
+            tasks.push(spawn(async move {
+                clone.retrieve_user(6484647, false).await.unwrap_or(None)
+            }));
+        }
+        for task in tasks {
+            assert!(task.await?.is_some());
+        }
+        assert!(start.elapsed().as_secs_f64() >= 60.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_retrieve_user() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/users/6484647/osu?key=id"),
+                200,
+                fixture("user").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/users/Sombrax79/osu?key=username"),
+                200,
+                fixture("user").await?,
+            );
+        let mut client = setup_stub_test(4, &transport).await?;
+        let user = client.retrieve_user(6484647, false).await?.unwrap();
+        assert_eq!(user.country_code, "MX");
+        assert_eq!(user.id, 6484647);
+        assert_eq!(user.previous_usernames, ["Sombra_X79"]);
+        assert_eq!(user.username, "Sombrax79");
+        let statistics = user.standard_statistics().unwrap();
+        assert_eq!(statistics.global_rank, Some(24871));
+        assert_eq!(statistics.country_rank, Some(412));
+        assert_eq!(statistics.play_count, 58234);
+        assert_eq!(statistics.pp, 6123.41);
+        assert_eq!(
+            client
+                .retrieve_user("Sombrax79", true)
+                .await?
+                .unwrap()
+                .username,
+            "Sombrax79"
+        );
+        assert!(client.retrieve_user(6484647, true).await?.is_none());
+        assert!(client.retrieve_user("Sombrax79", false).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_retry_server_error() -> Result<(), Box<dyn Error>> {
+        let url = format!("{STUB_URL}/api/v2/beatmaps/555797");
+        let transport = StubTransport::default()
+            .with_response(Method::GET, &url, 500, "")
+            .with_response(Method::GET, &url, 502, "<html></html>")
+            .with_response(Method::GET, &url, 200, fixture("beatmap").await?);
+        let mut client = setup_stub_test(10, &transport).await?;
+        assert_eq!(client.retrieve_beatmap(555797).await?.unwrap().id, 555797);
+        assert_eq!(
+            transport
+                .requests()
+                .iter()
+                .filter(|request| request.url == url)
+                .count(),
+            3
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_retry_rate_limited() -> Result<(), Box<dyn Error>> {
+        let url = format!("{STUB_URL}/api/v2/beatmaps/555797");
+        let transport = StubTransport::default();
+        transport.add_response(
+            Method::GET,
+            &url,
+            Response::new(429, "").with_header("Retry-After", "30"),
+        );
+        transport.add_response(
+            Method::GET,
+            &url,
+            Response::new(200, fixture("beatmap").await?),
+        );
+        let mut client = setup_stub_test(10, &transport).await?;
+        let start = time::Instant::now();
+        assert!(client.retrieve_beatmap(555797).await?.is_some());
+        assert!(start.elapsed() >= time::Duration::from_secs(30));
+        let transport = StubTransport::default();
+        transport.add_response(
+            Method::GET,
+            &url,
+            Response::new(429, "").with_header("Retry-After", "3600"),
+        );
+        let mut client = setup_stub_test(10, &transport).await?;
+        assert!(matches!(
+            client.retrieve_beatmap(555797).await,
+            Err(OsuApiError::RateLimited {
+                retry_after: Some(retry_after)
+            }) if retry_after == Duration::from_secs(3600)
+        ));
+        assert_eq!(
+            transport
+                .requests()
+                .iter()
+                .filter(|request| request.url == url)
+                .count(),
+            1
+        );
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_retry_unauthorized() -> Result<(), Box<dyn Error>> {
+        let url = format!("{STUB_URL}/api/v2/users/6484647/osu?key=id");
+        let transport = StubTransport::default()
+            .with_response(Method::GET, &url, 401, "")
+            .with_response(Method::GET, &url, 200, fixture("user").await?);
+        let mut client = setup_stub_test(10, &transport).await?;
+        assert!(client.retrieve_user(6484647, false).await?.is_some());
+        let token_requests = transport
+            .requests()
+            .iter()
+            .filter(|request| request.method == Method::POST)
+            .count();
+        assert_eq!(token_requests, 2);
+        let transport = StubTransport::default().with_response(Method::GET, &url, 401, "");
+        let mut client = setup_stub_test(10, &transport).await?;
+        assert!(matches!(
+            client.retrieve_user(6484647, false).await,
+            Err(OsuApiError::Unauthorized)
+        ));
+        assert_eq!(transport.requests().len(), 4);
+        let transport = StubTransport::default().with_response(
+            Method::POST,
+            format!("{STUB_URL}/oauth/token"),
+            401,
+            "",
+        );
+        let mut client = Client::new(
+            Configuration::new(
+                STUB_URL,
+                String::from("client_id"),
+                String::from("client_secret"),
+                10,
+            ),
+            transport.clone(),
+        );
+        assert!(matches!(
+            client.retrieve_user(6484647, false).await,
+            Err(OsuApiError::Unauthorized)
+        ));
+        assert_eq!(transport.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_retries_exhausted() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default().with_response(
+            Method::GET,
+            format!("{STUB_URL}/api/v2/beatmaps/555797"),
+            503,
+            "",
+        );
+        let mut client = setup_stub_test(10, &transport).await?;
+        match client.retrieve_beatmap(555797).await {
+            Err(OsuApiError::RetriesExhausted { attempts, error }) => {
+                assert_eq!(attempts, 4);
+                assert!(matches!(*error, OsuApiError::Server(503)));
+            }
+            _ => panic!("expected the retries to run out"),
+        }
+        assert_eq!(transport.requests().len(), 5);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_stub_authorization_code_flow() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::POST,
+                format!("{STUB_URL}/oauth/token"),
+                200,
+                fixture("authorization_token").await?,
+            )
+            .with_response(Method::GET, format!("{STUB_URL}/api/v2/me/osu"), 503, "")
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/me/osu"),
+                200,
+                fixture("user").await?,
+            );
+        let client = Client::new(
+            Configuration::new(
+                STUB_URL,
+                String::from("client_id"),
+                String::from("client_secret"),
+                2,
+            ),
+            transport.clone(),
+        );
+        let redirect_uri = "http://localhost:8080/api/authentication/osu/callback";
+        assert_eq!(
+            client.authorization_url(redirect_uri, "state")?,
+            format!(
+                "{STUB_URL}/oauth/authorize?client_id=client_id&redirect_uri=http%3A%2F%2Flocalhost%3A8080%2Fapi%2Fauthentication%2Fosu%2Fcallback&response_type=code&scope=identify&state=state"
+            )
+        );
+        let token = client.authorize("code", redirect_uri).await?;
+        assert_eq!(token.access_token, "user_access");
+        assert_eq!(token.refresh_token.as_deref(), Some("user_refresh"));
+        let user = client.retrieve_authorized_user(&token).await?;
+        assert_eq!(user.id, 6484647);
+        assert_eq!(user.username, "Sombrax79");
+        let requests = transport.requests();
+        let body: serde_json::Value =
+            serde_json::from_slice(requests[0].body.as_deref().unwrap_or_default())?;
+        assert_eq!(body["code"], "code");
+        assert_eq!(body["grant_type"], "authorization_code");
+        assert_eq!(body["redirect_uri"], redirect_uri);
+        assert_eq!(requests.len(), 3);
+        assert!(requests[2].headers.contains(&(
+            String::from("Authorization"),
+            String::from("Bearer user_access")
+        )));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_cache() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/users/6484647/osu?key=id"),
+                200,
+                fixture("user").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                200,
+                fixture("beatmap").await?,
+            );
+        let mut client = setup_stub_test(10, &transport).await?.with_cache(
+            MemoryCache::new(10),
+            CacheTtls::default().with(Endpoint::User, Duration::from_secs(60)),
+        );
+        for _ in 0..2 {
+            assert!(client.retrieve_user(6484647, false).await?.is_some());
+            assert!(client.retrieve_beatmap(555797).await?.is_some());
+        }
+        assert!(client.retrieve_user(6484647, true).await?.is_none());
+        assert!(client.retrieve_user(6484647, true).await?.is_none());
+        let urls: Vec<String> = transport
+            .requests()
+            .into_iter()
+            .map(|request| request.url)
+            .filter(|url| url.contains("/api/v2/"))
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                format!("{STUB_URL}/api/v2/users/6484647/osu?key=id"),
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                format!("{STUB_URL}/api/v2/users/6484647/osu?key=username"),
+                format!("{STUB_URL}/api/v2/users/6484647/osu?key=username"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_cache_beatmaps() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=555797"),
+                200,
+                fixture("beatmaps").await?,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=1"),
+                200,
+                r#"{"beatmaps":[]}"#,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                200,
+                fixture("beatmap").await?,
+            );
+        let mut client = setup_stub_test(10, &transport).await?.with_cache(
+            MemoryCache::new(10),
+            CacheTtls::default()
+                .with(Endpoint::Beatmap, Duration::from_secs(60))
+                .with(Endpoint::Beatmaps, Duration::from_secs(60)),
+        );
+        assert_eq!(client.retrieve_beatmaps(&[555797]).await?.len(), 1);
+        let beatmaps = client.retrieve_beatmaps(&[555797, 1]).await?;
+        assert_eq!(beatmaps.len(), 1);
+        assert_eq!(beatmaps[0].id, 555797);
+        assert_eq!(client.retrieve_beatmap(555797).await?.unwrap().id, 555797);
+        let urls: Vec<String> = transport
+            .requests()
+            .into_iter()
+            .map(|request| request.url)
+            .filter(|url| url.contains("/api/v2/"))
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=555797"),
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=1"),
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stub_classified_errors() -> Result<(), Box<dyn Error>> {
+        let transport = StubTransport::default()
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps/555797"),
+                200,
+                r#"{"id":"555797"}"#,
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmaps?ids[]=555797"),
+                404,
+                "",
+            )
+            .with_response(
+                Method::GET,
+                format!("{STUB_URL}/api/v2/beatmapsets/158023"),
+                403,
+                "",
+            );
+        let mut client = setup_stub_test(10, &transport).await?;
+        match client.retrieve_beatmap(555797).await {
+            Err(OsuApiError::Decode {
+                body_snippet,
+                endpoint,
+                ..
+            }) => {
+                assert_eq!(body_snippet, r#"{"id":"555797"}"#);
+                assert_eq!(endpoint, "beatmaps/555797");
+            }
+            _ => panic!("expected a decode error"),
+        }
+        assert!(matches!(
+            client.retrieve_beatmaps(&[555797]).await,
+            Err(OsuApiError::NotFound)
+        ));
+        assert!(matches!(
+            client.retrieve_beatmapset(158023).await,
+            Err(OsuApiError::Status(403))
+        ));
+        assert_eq!(transport.requests().len(), 4);
+        Ok(())
+    }
 }
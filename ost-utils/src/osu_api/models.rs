@@ -1,7 +1,33 @@
+use super::Error;
 use chrono::{DateTime, Utc};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
+use std::{env, time::Duration};
 
-#[derive(Deserialize)]
+/// The URL of the official osu! servers.
+pub const OSU_URL: &str = "https://osu.ppy.sh";
+
+/// The body of the request exchanging an OAuth authorization code for a user [`Token`].
+#[derive(Serialize)]
+pub struct AuthorizationCode<'a> {
+    pub client_id: &'a str,
+    pub client_secret: &'a str,
+    pub code: &'a str,
+    pub grant_type: &'a str,
+    pub redirect_uri: &'a str,
+}
+
+/// The query string of the osu! page where users authorize the OAuth application.
+#[derive(Serialize)]
+pub struct AuthorizationRequest<'a> {
+    pub client_id: &'a str,
+    pub redirect_uri: &'a str,
+    pub response_type: &'a str,
+    pub scope: &'a str,
+    pub state: &'a str,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct Beatmap {
     pub beatmapset: Option<Beatmapset>,
     pub beatmapset_id: i32,
@@ -9,50 +35,140 @@ pub struct Beatmap {
     pub id: i32,
     pub last_updated: DateTime<Utc>,
     pub mode: String,
-    pub ranked: i8,
+    pub ranked: RankedStatus,
     pub total_length: i16,
     pub version: String,
 }
 
 impl Beatmap {
     pub fn is_ranked(&self) -> bool {
-        self.ranked == 1 || self.ranked == 2
+        self.ranked.is_ranked()
     }
 
     pub fn is_loved(&self) -> bool {
-        self.ranked == 4
+        self.ranked == RankedStatus::Loved
     }
 
     pub fn is_standard(&self) -> bool {
         self.mode == "osu"
     }
 }
+
+/// The difficulty attributes of an osu!standard beatmap computed by osu!.
+#[derive(Clone, Debug, Deserialize)]
+pub struct BeatmapAttributes {
+    pub aim_difficulty: Option<f32>,
+    pub approach_rate: Option<f32>,
+    pub max_combo: i32,
+    pub overall_difficulty: Option<f32>,
+    pub speed_difficulty: Option<f32>,
+    pub star_rating: f32,
+}
+
+#[derive(Serialize)]
+pub struct BeatmapAttributesQuery<'a> {
+    pub mods: u32,
+    pub ruleset: &'a str,
+}
+
+#[derive(Deserialize)]
+pub struct BeatmapDifficultyAttributes {
+    pub attributes: BeatmapAttributes,
+}
+
+/// The score of a user on a beatmap, along with its position on the leaderboard.
+#[derive(Deserialize)]
+pub struct BeatmapUserScore {
+    pub position: i32,
+    pub score: Score,
+}
+
 #[derive(Deserialize)]
-pub struct Beatmaps {
-    pub beatmaps: Vec<Beatmap>,
+pub struct BeatmapUserScores {
+    pub scores: Vec<Score>,
 }
 
 #[derive(Deserialize)]
+pub struct Beatmaps<T = Beatmap> {
+    pub beatmaps: Vec<T>,
+}
+
+#[derive(Clone, Deserialize)]
 pub struct Beatmapset {
+    pub artist: String,
+    pub artist_unicode: String,
     pub beatmaps: Option<Vec<Beatmap>>,
+    /// The average BPM of the beatmapset, only included in full beatmapsets.
+    pub bpm: Option<f32>,
+    pub covers: Covers,
+    /// The username of the mapper.
+    pub creator: String,
     pub favourite_count: i32,
+    /// Only included when retrieving a single beatmapset.
+    pub genre: Option<Descriptor>,
     pub id: i32,
+    /// Only included when retrieving a single beatmapset.
+    pub language: Option<Descriptor>,
+    pub nsfw: bool,
     pub play_count: i32,
-    pub ranked: i8,
+    pub ranked: RankedStatus,
     pub ranked_date: Option<DateTime<Utc>>,
+    pub source: String,
+    /// The space-separated search tags, only included in full beatmapsets.
+    #[serde(default)]
+    pub tags: String,
     pub title: String,
+    pub title_unicode: String,
+    /// The user id of the mapper.
+    pub user_id: i32,
+    pub video: bool,
 }
 
 impl Beatmapset {
     pub fn is_ranked(&self) -> bool {
-        self.ranked == 1 || self.ranked == 2
+        self.ranked.is_ranked()
     }
 
     pub fn is_loved(&self) -> bool {
-        self.ranked == 4
+        self.ranked == RankedStatus::Loved
+    }
+
+    /// Returns the osu!standard beatmaps of this beatmapset, each of them including the beatmapset
+    /// without its beatmaps.
+    pub fn into_standard_beatmaps(mut self) -> Vec<Beatmap> {
+        let beatmaps = self.beatmaps.take().unwrap_or_default();
+        beatmaps
+            .into_iter()
+            .filter(Beatmap::is_standard)
+            .map(|beatmap| Beatmap {
+                beatmapset: Some(self.clone()),
+                ..beatmap
+            })
+            .collect()
     }
 }
 
+/// The beatmapset included in other osu! API objects, such as scores, which lacks the ranked
+/// status, dates and other fields of a full [`Beatmapset`].
+#[derive(Clone, Deserialize)]
+pub struct BeatmapsetCompact {
+    pub artist: String,
+    pub artist_unicode: String,
+    pub covers: Covers,
+    /// The username of the mapper.
+    pub creator: String,
+    pub favourite_count: i32,
+    pub id: i32,
+    pub nsfw: bool,
+    pub play_count: i32,
+    pub source: String,
+    pub title: String,
+    pub title_unicode: String,
+    /// The user id of the mapper.
+    pub user_id: i32,
+    pub video: bool,
+}
+
 #[derive(Deserialize)]
 pub struct ClientToken {
     pub access_token: String,
@@ -60,10 +176,84 @@ pub struct ClientToken {
     pub token_type: String,
 }
 
-#[derive(Deserialize)]
-pub struct Cursor {
-    pub approved_date: String,
-    pub id: String,
+/// The URLs of the artwork of a beatmapset in its different sizes.
+#[derive(Clone, Deserialize)]
+pub struct Covers {
+    pub card: String,
+    pub cover: String,
+    pub list: String,
+    pub slimcover: String,
+}
+
+/// The settings required to create an osu! API [`Client`](super::Client).
+#[derive(Clone)]
+pub struct Configuration {
+    /// The base URL of the osu! servers, without a trailing slash.
+    pub base_url: String,
+    /// The largest burst of requests allowed by the rate limiter.
+    pub burst: usize,
+    pub client_id: String,
+    pub client_secret: String,
+    pub requests_per_minute: usize,
+    /// The policy used to retry failed requests.
+    pub retry_policy: RetryPolicy,
+    /// The absolute URL of the OAuth token endpoint.
+    pub token_url: String,
+}
+
+impl Configuration {
+    /// Creates a new [`Configuration`] whose token endpoint is derived from the `base_url`, without
+    /// bursts of requests so that no more than `requests_per_minute` are performed in a minute.
+    pub fn new(
+        base_url: &str,
+        client_id: String,
+        client_secret: String,
+        requests_per_minute: usize,
+    ) -> Self {
+        let base_url = String::from(base_url.trim_end_matches('/'));
+        Self {
+            token_url: format!("{base_url}/oauth/token"),
+            base_url,
+            burst: 1,
+            client_id,
+            client_secret,
+            requests_per_minute,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Creates a new [`Configuration`] from environment variables.
+    ///
+    /// The credentials are read from `OSU_API_CLIENT_ID` and `OSU_API_CLIENT_SECRET`, while the
+    /// optional `OSU_API_URL` and `OSU_API_TOKEN_URL` override the official osu! servers and the
+    /// optional `OSU_API_REQUESTS_PER_MINUTE` and `OSU_API_BURST` override the rate limits.
+    pub fn from_environment(requests_per_minute: usize) -> Result<Self, Error> {
+        let mut configuration = Self::new(
+            &env::var("OSU_API_URL")
+                .ok()
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(|| String::from(OSU_URL)),
+            env::var("OSU_API_CLIENT_ID")?,
+            env::var("OSU_API_CLIENT_SECRET")?,
+            match env::var("OSU_API_REQUESTS_PER_MINUTE") {
+                Ok(requests) if !requests.is_empty() => requests.parse()?,
+                _ => requests_per_minute,
+            },
+        );
+        if let Some(burst) = env::var("OSU_API_BURST")
+            .ok()
+            .filter(|burst| !burst.is_empty())
+        {
+            configuration.burst = burst.parse()?;
+        }
+        if let Some(token_url) = env::var("OSU_API_TOKEN_URL")
+            .ok()
+            .filter(|url| !url.is_empty())
+        {
+            configuration.token_url = token_url;
+        }
+        Ok(configuration)
+    }
 }
 
 #[derive(Clone, Serialize)]
@@ -73,6 +263,14 @@ pub struct Credentials {
     pub grant_type: String,
     pub scope: String,
 }
+
+/// A named category of a beatmapset, such as its genre or language.
+#[derive(Clone, Deserialize)]
+pub struct Descriptor {
+    pub id: i32,
+    pub name: String,
+}
+
 pub struct Headers {
     pub token: String,
     pub expiration: DateTime<Utc>,
@@ -84,21 +282,367 @@ impl Headers {
     }
 }
 
+/// The genres used to filter a [`SearchQuery`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Genre {
+    Unspecified = 1,
+    VideoGame = 2,
+    Anime = 3,
+    Rock = 4,
+    Pop = 5,
+    Other = 6,
+    Novelty = 7,
+    HipHop = 9,
+    Electronic = 10,
+    Metal = 11,
+    Classical = 12,
+    Folk = 13,
+    Jazz = 14,
+}
+
+/// The languages used to filter a [`SearchQuery`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Language {
+    Unspecified = 1,
+    English = 2,
+    Japanese = 3,
+    Chinese = 4,
+    Instrumental = 5,
+    Korean = 6,
+    French = 7,
+    German = 8,
+    Swedish = 9,
+    Spanish = 10,
+    Italian = 11,
+    Russian = 12,
+    Polish = 13,
+    Other = 14,
+}
+
+/// The osu! game modes.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    Osu = 0,
+    Taiko = 1,
+    Fruits = 2,
+    Mania = 3,
+}
+
+impl Mode {
+    /// Returns the name of the mode used by the osu! API routes.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Osu => "osu",
+            Self::Taiko => "taiko",
+            Self::Fruits => "fruits",
+            Self::Mania => "mania",
+        }
+    }
+}
+
+/// The ranked status of a beatmap or beatmapset, as returned by the osu! API.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq)]
+#[serde(try_from = "i8")]
+pub enum RankedStatus {
+    Graveyard = -2,
+    Wip = -1,
+    Pending = 0,
+    Ranked = 1,
+    Approved = 2,
+    Qualified = 3,
+    Loved = 4,
+}
+
+impl RankedStatus {
+    /// Returns whether the status awards performance points, i.e., ranked or approved.
+    pub fn is_ranked(self) -> bool {
+        matches!(self, Self::Ranked | Self::Approved)
+    }
+}
+
+impl TryFrom<i8> for RankedStatus {
+    type Error = String;
+
+    fn try_from(ranked: i8) -> Result<Self, Self::Error> {
+        Ok(match ranked {
+            -2 => Self::Graveyard,
+            -1 => Self::Wip,
+            0 => Self::Pending,
+            1 => Self::Ranked,
+            2 => Self::Approved,
+            3 => Self::Qualified,
+            4 => Self::Loved,
+            _ => return Err(format!("invalid ranked status {ranked}")),
+        })
+    }
+}
+
+/// A score set by a user.
 #[derive(Deserialize)]
-pub struct LeaderboardBeatmapsets {
+pub struct Score {
+    /// The accuracy of the score, between 0 and 1.
+    pub accuracy: f32,
+    /// Only included when the score is not retrieved through its beatmap.
+    pub beatmap: Option<Beatmap>,
+    /// Only included when the score is not retrieved through its beatmap.
+    pub beatmapset: Option<BeatmapsetCompact>,
+    pub created_at: DateTime<Utc>,
+    pub id: i64,
+    pub max_combo: i32,
+    pub mode: String,
+    /// The acronyms of the mods of the score, e.g., `HD` or `DT`.
+    pub mods: Vec<String>,
+    pub passed: bool,
+    pub perfect: bool,
+    /// The performance points of the score, missing for scores on unranked beatmaps.
+    pub pp: Option<f32>,
+    /// The grade of the score, e.g., `S` or `XH`.
+    pub rank: String,
+    pub score: i64,
+    pub statistics: ScoreStatistics,
+    pub user_id: i32,
+}
+
+impl Score {
+    /// Returns whether the score was set with the mod with the specified `acronym`.
+    pub fn has_mod(&self, acronym: &str) -> bool {
+        self.mods.iter().any(|score_mod| score_mod == acronym)
+    }
+}
+
+/// The hit counts of a [`Score`].
+#[derive(Deserialize)]
+pub struct ScoreStatistics {
+    pub count_100: i32,
+    pub count_300: i32,
+    pub count_50: i32,
+    pub count_geki: i32,
+    pub count_katu: i32,
+    pub count_miss: i32,
+}
+
+/// The lists of scores of a user.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScoreType {
+    /// The top scores by performance points.
+    Best,
+    /// The scores that are first place on the leaderboard of their beatmap.
+    Firsts,
+    /// The scores set in the last 24 hours.
+    Recent,
+}
+
+impl ScoreType {
+    /// Returns the name of the list used by the osu! API routes.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Best => "best",
+            Self::Firsts => "firsts",
+            Self::Recent => "recent",
+        }
+    }
+}
+
+/// A page of beatmapsets returned by a beatmapset search.
+#[derive(Deserialize)]
+pub struct SearchPage {
     pub beatmapsets: Vec<Beatmapset>,
-    pub cursor: Option<Cursor>,
+    /// The opaque cursor of the next page, if any.
+    pub cursor_string: Option<String>,
+}
+
+/// The options of a beatmapset search, sent as the query string of the request.
+///
+/// An empty query searches the beatmapsets with a leaderboard of every mode, sorted by relevance.
+#[derive(Clone, Default, Serialize)]
+pub struct SearchQuery {
+    #[serde(rename = "q", skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(rename = "m", skip_serializing_if = "Option::is_none")]
+    mode: Option<u8>,
+    #[serde(rename = "s", skip_serializing_if = "Option::is_none")]
+    status: Option<SearchStatus>,
+    #[serde(rename = "g", skip_serializing_if = "Option::is_none")]
+    genre: Option<u8>,
+    #[serde(rename = "l", skip_serializing_if = "Option::is_none")]
+    language: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nsfw: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sort: Option<SearchSort>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor_string: Option<String>,
+}
+
+impl SearchQuery {
+    /// Resumes the search from the opaque `cursor_string` of a previous [`SearchPage`].
+    pub fn cursor_string(mut self, cursor_string: Option<String>) -> Self {
+        self.cursor_string = cursor_string;
+        self
+    }
+
+    pub fn genre(mut self, genre: Genre) -> Self {
+        self.genre = Some(genre as u8);
+        self
+    }
+
+    pub fn language(mut self, language: Language) -> Self {
+        self.language = Some(language as u8);
+        self
+    }
+
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = Some(mode as u8);
+        self
+    }
+
+    /// Sets whether explicit beatmapsets are included, which osu! excludes by default.
+    pub fn nsfw(mut self, nsfw: bool) -> Self {
+        self.nsfw = Some(nsfw);
+        self
+    }
+
+    pub fn sort(mut self, sort: SearchSort) -> Self {
+        self.sort = Some(sort);
+        self
+    }
+
+    pub fn status(mut self, status: SearchStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sets the text matched against the title, artist, mapper, tags and source of the beatmapsets.
+    pub fn text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+}
+
+/// The orders of the results of a [`SearchQuery`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchSort {
+    ArtistAsc,
+    ArtistDesc,
+    DifficultyAsc,
+    DifficultyDesc,
+    FavouritesAsc,
+    FavouritesDesc,
+    PlaysAsc,
+    PlaysDesc,
+    RankedAsc,
+    RankedDesc,
+    RatingAsc,
+    RatingDesc,
+    TitleAsc,
+    TitleDesc,
+    UpdatedAsc,
+    UpdatedDesc,
+}
+
+/// The statuses used to filter a [`SearchQuery`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchStatus {
+    Any,
+    Graveyard,
+    /// The beatmapsets with a leaderboard, i.e., ranked, approved, qualified or loved.
+    Leaderboard,
+    Loved,
+    Pending,
+    Qualified,
+    Ranked,
+    Wip,
+}
+
+/// The policy used to retry osu! API requests that failed because of network errors,
+/// rate limiting, expired tokens or server errors.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    /// The delay before the first retry, doubled on every following attempt.
+    pub base_delay: Duration,
+    /// The upper bound of the delay between attempts.
+    pub max_delay: Duration,
+    /// The number of retries performed before giving up.
+    pub max_retries: u32,
+}
+
+impl RetryPolicy {
+    /// Returns the exponential delay with jitter to wait before the retry number `attempt`,
+    /// starting from 1.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay);
+        delay / 2 + delay.mul_f64(rand::thread_rng().gen_range(0.0..0.5))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            max_retries: 3,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Token {
     pub access_token: String,
     pub expires_in: u64,
+    /// The token used to renew a user [`Token`], only issued by the authorization code flow.
+    pub refresh_token: Option<String>,
     pub token_type: String,
 }
 
 #[derive(Deserialize)]
 pub struct User {
+    pub avatar_url: String,
     pub country_code: String,
+    pub id: i32,
+    /// Only included when retrieving a single user.
+    #[serde(default)]
+    pub previous_usernames: Vec<String>,
+    /// The osu!standard statistics, only included when retrieving a single user.
+    pub statistics: Option<UserStatistics>,
+    /// The statistics of every mode, only included when retrieving several users.
+    pub statistics_rulesets: Option<UserStatisticsRulesets>,
     pub username: String,
 }
+
+impl User {
+    /// Returns the osu!standard statistics of the user, regardless of how it was retrieved.
+    pub fn standard_statistics(&self) -> Option<&UserStatistics> {
+        self.statistics.as_ref().or_else(|| {
+            self.statistics_rulesets
+                .as_ref()
+                .and_then(|rulesets| rulesets.osu.as_ref())
+        })
+    }
+}
+
+/// The statistics of a [`User`] in a mode.
+#[derive(Deserialize)]
+pub struct UserStatistics {
+    /// The rank within the country of the user, missing for inactive users.
+    pub country_rank: Option<i32>,
+    /// The global rank, missing for inactive users.
+    pub global_rank: Option<i32>,
+    pub play_count: i32,
+    pub pp: f32,
+}
+
+/// The statistics of a [`User`] in every mode.
+#[derive(Deserialize)]
+pub struct UserStatisticsRulesets {
+    pub osu: Option<UserStatistics>,
+}
+
+#[derive(Deserialize)]
+pub struct Users {
+    pub users: Vec<User>,
+}
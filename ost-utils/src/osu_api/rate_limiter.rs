@@ -0,0 +1,276 @@
+use super::Error;
+use async_trait::async_trait;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::time::{sleep, Instant};
+
+/// A snapshot of the state of a [`RateLimiter`].
+#[derive(Clone, Copy, Debug)]
+pub struct RateLimiterMetrics {
+    /// The tokens currently available, negative when requests are waiting for a token.
+    pub available_tokens: f64,
+    /// The maximum number of tokens, i.e., the largest burst of requests allowed.
+    pub capacity: f64,
+    /// The number of requests that went through the limiter.
+    pub requests: u64,
+    /// The number of requests that had to wait for a token.
+    pub throttled_requests: u64,
+    /// The accumulated time that requests spent waiting for a token.
+    pub total_wait: Duration,
+}
+
+/// Limits the rate of the requests performed by the osu! API [`Client`](super::Client).
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// Waits until a request can be performed, returning the time waited.
+    async fn acquire(&self) -> Result<Duration, Error>;
+
+    /// Returns a snapshot of the current state of the [`RateLimiter`].
+    fn metrics(&self) -> RateLimiterMetrics;
+}
+
+/// The request counters shared by the [`RateLimiter`] implementations.
+#[derive(Default)]
+struct Counters {
+    requests: AtomicU64,
+    throttled_requests: AtomicU64,
+    total_wait: AtomicU64,
+}
+
+impl Counters {
+    /// Records a request that waited `wait` for a token.
+    fn record(&self, wait: Duration) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !wait.is_zero() {
+            self.throttled_requests.fetch_add(1, Ordering::Relaxed);
+            self.total_wait
+                .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the [`RateLimiterMetrics`] for the specified bucket state.
+    fn metrics(&self, available_tokens: f64, capacity: f64) -> RateLimiterMetrics {
+        RateLimiterMetrics {
+            available_tokens,
+            capacity,
+            requests: self.requests.load(Ordering::Relaxed),
+            throttled_requests: self.throttled_requests.load(Ordering::Relaxed),
+            total_wait: Duration::from_micros(self.total_wait.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// The mutable state of a [`TokenBucket`].
+struct Bucket {
+    last_refill: Instant,
+    tokens: f64,
+}
+
+/// An in-process token bucket [`RateLimiter`] holding up to `burst` tokens.
+pub struct TokenBucket {
+    bucket: Mutex<Bucket>,
+    capacity: f64,
+    counters: Counters,
+    /// The tokens refilled per second.
+    refill_rate: f64,
+}
+
+impl TokenBucket {
+    /// Creates a new full [`TokenBucket`].
+    pub fn new(burst: usize, requests_per_minute: usize) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            bucket: Mutex::new(Bucket {
+                last_refill: Instant::now(),
+                tokens: capacity,
+            }),
+            capacity,
+            counters: Counters::default(),
+            refill_rate: requests_per_minute.max(1) as f64 / 60.0,
+        }
+    }
+
+    /// Refills the `bucket` up to the current instant.
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        bucket.tokens = (bucket.tokens
+            + now.duration_since(bucket.last_refill).as_secs_f64() * self.refill_rate)
+            .min(self.capacity);
+        bucket.last_refill = now;
+    }
+
+    /// Takes a token from the bucket, returning how long to wait until it is available.
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-bucket.tokens / self.refill_rate)
+        }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for TokenBucket {
+    async fn acquire(&self) -> Result<Duration, Error> {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+        self.counters.record(wait);
+        Ok(wait)
+    }
+
+    fn metrics(&self) -> RateLimiterMetrics {
+        let mut bucket = self.bucket.lock().unwrap();
+        self.refill(&mut bucket);
+        self.counters.metrics(bucket.tokens, self.capacity)
+    }
+}
+
+#[cfg(feature = "redis")]
+pub use self::redis_bucket::RedisTokenBucket;
+
+#[cfg(feature = "redis")]
+mod redis_bucket {
+    use super::{Counters, Error, RateLimiter, RateLimiterMetrics};
+    use async_trait::async_trait;
+    use redis::{aio::ConnectionManager, Script};
+    use std::{sync::Mutex, time::Duration};
+    use tokio::time::sleep;
+
+    /// Takes a token from the bucket at `KEYS[1]`, returning the milliseconds to wait for it, or
+    /// `-1` with the stored burst and rate if they differ from the ones of the caller.
+    const RESERVE_SCRIPT: &str = r#"
+        redis.replicate_commands()
+        local capacity = tonumber(ARGV[1])
+        local refill_rate = tonumber(ARGV[2])
+        local configuration = ARGV[1] .. '/' .. ARGV[2]
+        local time = redis.call('TIME')
+        local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+        local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'last_refill', 'configuration')
+        if bucket[3] and bucket[3] ~= configuration then
+            return {-1, bucket[3]}
+        end
+        local tokens = tonumber(bucket[1]) or capacity
+        local last_refill = tonumber(bucket[2]) or now
+        tokens = math.min(capacity, tokens + math.max(0, now - last_refill) * refill_rate) - 1
+        redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'last_refill', now,
+            'configuration', configuration)
+        redis.call('PEXPIRE', KEYS[1], math.ceil((capacity - tokens) / refill_rate) + 60000)
+        local wait = 0
+        if tokens < 0 then
+            wait = math.ceil(-tokens / refill_rate)
+        end
+        return {wait, tostring(tokens)}
+    "#;
+
+    /// A token bucket [`RateLimiter`] stored in Redis, shared by every process using the same
+    /// `key`.
+    pub struct RedisTokenBucket {
+        capacity: f64,
+        connection: ConnectionManager,
+        counters: Counters,
+        key: String,
+        last_tokens: Mutex<f64>,
+        /// The tokens refilled per millisecond.
+        refill_rate: f64,
+        script: Script,
+    }
+
+    impl RedisTokenBucket {
+        /// Creates a new [`RedisTokenBucket`] stored at `key`.
+        pub fn new(
+            burst: usize,
+            connection: ConnectionManager,
+            key: impl Into<String>,
+            requests_per_minute: usize,
+        ) -> Self {
+            let capacity = burst.max(1) as f64;
+            Self {
+                capacity,
+                connection,
+                counters: Counters::default(),
+                key: key.into(),
+                last_tokens: Mutex::new(capacity),
+                refill_rate: requests_per_minute.max(1) as f64 / 60_000.0,
+                script: Script::new(RESERVE_SCRIPT),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl RateLimiter for RedisTokenBucket {
+        async fn acquire(&self) -> Result<Duration, Error> {
+            let (wait, tokens) = self
+                .script
+                .key(&self.key)
+                .arg(self.capacity)
+                .arg(self.refill_rate)
+                .invoke_async::<_, (i64, String)>(&mut self.connection.clone())
+                .await?;
+            if wait < 0 {
+                return Err(Error::RateLimiterMismatch(tokens));
+            }
+            *self.last_tokens.lock().unwrap() = tokens.parse().unwrap_or_default();
+            let wait = Duration::from_millis(wait as u64);
+            if !wait.is_zero() {
+                sleep(wait).await;
+            }
+            self.counters.record(wait);
+            Ok(wait)
+        }
+
+        fn metrics(&self) -> RateLimiterMetrics {
+            self.counters
+                .metrics(*self.last_tokens.lock().unwrap(), self.capacity)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RateLimiter, TokenBucket};
+    use std::{error::Error, time::Duration};
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket_burst() -> Result<(), Box<dyn Error>> {
+        let bucket = TokenBucket::new(3, 60);
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert_eq!(bucket.acquire().await?, Duration::ZERO);
+        }
+        assert_eq!(start.elapsed(), Duration::ZERO);
+        assert_eq!(bucket.acquire().await?, Duration::from_secs(1));
+        assert_eq!(bucket.acquire().await?, Duration::from_secs(1));
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+        let metrics = bucket.metrics();
+        assert_eq!(metrics.capacity, 3.0);
+        assert_eq!(metrics.requests, 5);
+        assert_eq!(metrics.throttled_requests, 2);
+        assert_eq!(metrics.total_wait, Duration::from_secs(2));
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_token_bucket_refill() -> Result<(), Box<dyn Error>> {
+        let bucket = TokenBucket::new(2, 30);
+        bucket.acquire().await?;
+        bucket.acquire().await?;
+        assert!(bucket.metrics().available_tokens.abs() < f64::EPSILON);
+        tokio::time::advance(Duration::from_secs(2)).await;
+        assert!((bucket.metrics().available_tokens - 1.0).abs() < 1e-6);
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(bucket.metrics().available_tokens, 2.0);
+        assert_eq!(bucket.acquire().await?, Duration::ZERO);
+        Ok(())
+    }
+}
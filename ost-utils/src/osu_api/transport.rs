@@ -0,0 +1,178 @@
+use super::Error;
+use async_trait::async_trait;
+use reqwest::Method;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+/// An HTTP request sent by the osu! API [`Client`](super::Client) through a [`Transport`].
+#[derive(Clone, Debug)]
+pub struct Request {
+    /// The raw body of the request, if any.
+    pub body: Option<Vec<u8>>,
+    /// The headers of the request as name and value pairs.
+    pub headers: Vec<(String, String)>,
+    /// The HTTP method of the request.
+    pub method: Method,
+    /// The absolute URL of the request.
+    pub url: String,
+}
+
+impl Request {
+    /// Creates a new [`Request`] without headers or body.
+    pub fn new(method: Method, url: impl Into<String>) -> Self {
+        Self {
+            body: None,
+            headers: Vec::new(),
+            method,
+            url: url.into(),
+        }
+    }
+
+    /// Sets the raw body of the [`Request`].
+    pub fn body(mut self, body: Vec<u8>) -> Self {
+        self.body = Some(body);
+        self
+    }
+
+    /// Appends a header to the [`Request`].
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// An HTTP response returned by a [`Transport`].
+#[derive(Clone, Debug)]
+pub struct Response {
+    /// The raw body of the response.
+    pub body: Vec<u8>,
+    /// The headers of the response as name and value pairs.
+    pub headers: Vec<(String, String)>,
+    /// The HTTP status code of the response.
+    pub status: u16,
+}
+
+impl Response {
+    /// Creates a new [`Response`] without headers.
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            body: body.into(),
+            headers: Vec::new(),
+            status,
+        }
+    }
+
+    /// Returns the value of the first header matching `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Appends a header to the [`Response`].
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// The HTTP layer used by the osu! API [`Client`](super::Client) to reach the osu! servers.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends the `request` and returns its [`Response`], regardless of the status code.
+    async fn send(&self, request: Request) -> Result<Response, Error>;
+}
+
+/// The default [`Transport`], backed by a [`reqwest::Client`].
+#[derive(Clone, Default)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+#[async_trait]
+impl Transport for ReqwestTransport {
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let mut builder = self.client.request(request.method, request.url);
+        for (name, value) in request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+        let response = builder.send().await?;
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    String::from(name.as_str()),
+                    String::from(value.to_str().ok()?),
+                ))
+            })
+            .collect();
+        Ok(Response {
+            body: response.bytes().await?.to_vec(),
+            headers,
+            status,
+        })
+    }
+}
+
+/// The queued responses of a [`StubTransport`] for each method and URL.
+type StubResponses = HashMap<(Method, String), VecDeque<Response>>;
+
+/// An in-memory [`Transport`] that answers requests with preset responses, meant for offline tests.
+#[derive(Clone, Default)]
+pub struct StubTransport {
+    /// The requests received so far, in order.
+    requests: Arc<Mutex<Vec<Request>>>,
+    /// The pending responses for each method and URL.
+    responses: Arc<Mutex<StubResponses>>,
+}
+
+impl StubTransport {
+    /// Registers a `response` for the requests with the specified `method` and `url`.
+    pub fn add_response(&self, method: Method, url: impl Into<String>, response: Response) {
+        self.responses
+            .lock()
+            .unwrap()
+            .entry((method, url.into()))
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Returns the requests received so far, in order.
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Registers a response with the specified `status` and `body`, returning the [`StubTransport`].
+    pub fn with_response(
+        self,
+        method: Method,
+        url: impl Into<String>,
+        status: u16,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.add_response(method, url, Response::new(status, body));
+        self
+    }
+}
+
+#[async_trait]
+impl Transport for StubTransport {
+    async fn send(&self, request: Request) -> Result<Response, Error> {
+        let key = (request.method.clone(), request.url.clone());
+        self.requests.lock().unwrap().push(request);
+        let mut responses = self.responses.lock().unwrap();
+        Ok(match responses.get_mut(&key) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap(),
+            Some(queue) => queue.front().cloned().unwrap(),
+            None => Response::new(404, Vec::new()),
+        })
+    }
+}
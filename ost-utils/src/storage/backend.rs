@@ -0,0 +1,844 @@
+use super::{checksum, Error};
+use async_trait::async_trait;
+use aws_config::from_env;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_smithy_http::byte_stream::ByteStream;
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use http::{
+    header::{IF_MATCH, IF_NONE_MATCH},
+    HeaderValue, StatusCode,
+};
+use std::{
+    collections::HashMap,
+    io::{ErrorKind, SeekFrom},
+    path::{Component, Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
+};
+use tokio_util::io::ReaderStream;
+
+/// The age after which a lock file of a [`LocalBackend`] is considered left behind by a process that
+/// crashed during a conditional upload.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+/// The directory of a [`LocalBackend`] where the metadata of the objects is stored.
+const METADATA_DIRECTORY: &str = ".metadata";
+/// The size of the parts of the multipart uploads of an [`S3Backend`], above the 5 MiB minimum of
+/// AWS S3.
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// A stream of the chunks of the contents of an object.
+pub type ContentStream = Pin<Box<dyn Stream<Item = Result<Bytes, Error>> + Send>>;
+
+/// Returns a [`ContentStream`] of a single chunk with the `content`.
+pub fn content_stream(content: impl Into<Bytes>) -> ContentStream {
+    Box::pin(stream::once(futures::future::ready(Ok(content.into()))))
+}
+
+/// An inclusive range of the bytes of the contents of an object, like the HTTP `Range` header.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ByteRange {
+    /// The last byte of the range, or `None` to reach the end of the contents.
+    pub end: Option<u64>,
+    pub start: u64,
+}
+
+impl ByteRange {
+    pub fn new(start: u64, end: Option<u64>) -> Self {
+        Self { end, start }
+    }
+
+    /// Returns the HTTP `Range` header value of the range.
+    pub fn header(&self) -> String {
+        match self.end {
+            Some(end) => format!("bytes={}-{end}", self.start),
+            None => format!("bytes={}-", self.start),
+        }
+    }
+
+    /// Returns the number of bytes of the range, or `None` if it reaches the end of the contents.
+    pub fn length(&self) -> Option<u64> {
+        self.end.map(|end| (end + 1).saturating_sub(self.start))
+    }
+
+    /// Returns the part of the `content` within the range.
+    pub fn slice<'a>(&self, content: &'a [u8]) -> &'a [u8] {
+        let start = (self.start as usize).min(content.len());
+        let end = self.end.map_or(content.len(), |end| {
+            (end as usize).saturating_add(1).min(content.len())
+        });
+        &content[start..end.max(start)]
+    }
+}
+
+/// The metadata of an object as name and value pairs.
+pub type Metadata = HashMap<String, String>;
+
+/// An object stored by a [`Backend`].
+#[derive(Clone, Debug, Default)]
+pub struct Object {
+    pub content: Vec<u8>,
+    pub metadata: Metadata,
+    /// The entity tag identifying the stored contents, which changes whenever the object is
+    /// replaced.
+    pub tag: Option<String>,
+}
+
+/// The state an object must be in for a conditional upload of a [`Backend`] to replace it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Precondition {
+    /// The object must not exist.
+    Absent,
+    /// The object must exist with the specified entity tag.
+    Tag(String),
+}
+
+impl Precondition {
+    /// Returns the [`Precondition`] that the object is still in the state it was retrieved in,
+    /// with the entity `tag`, if it existed.
+    pub fn from_tag(tag: Option<String>) -> Self {
+        tag.map_or(Self::Absent, Self::Tag)
+    }
+
+    /// Returns whether an object with the entity `tag`, if it exists, meets the precondition.
+    pub fn is_met(&self, tag: Option<&str>) -> bool {
+        match self {
+            Self::Absent => tag.is_none(),
+            Self::Tag(expected) => tag == Some(expected.as_str()),
+        }
+    }
+}
+
+/// An object stored by a [`Backend`] whose contents are streamed.
+pub struct ObjectStream {
+    pub content: ContentStream,
+    pub metadata: Metadata,
+}
+
+/// A page of the keys of the objects listed by a [`Backend`], in lexicographical order.
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    /// The opaque token to list the next page, or `None` if this is the last page.
+    pub continuation: Option<String>,
+    pub keys: Vec<String>,
+}
+
+impl Page {
+    /// Returns the page of up to `limit` of the `keys` starting with `prefix` that follows the
+    /// `continuation`, which is the last key of the previous page.
+    fn from_keys(
+        continuation: Option<&str>,
+        keys: impl IntoIterator<Item = String>,
+        limit: usize,
+        prefix: &str,
+    ) -> Self {
+        let mut keys: Vec<String> = keys
+            .into_iter()
+            .filter(|key| key.starts_with(prefix))
+            .filter(
+                |key| !matches!(continuation, Some(continuation) if key.as_str() <= continuation),
+            )
+            .collect();
+        keys.sort_unstable();
+        let continuation = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+        Self { continuation, keys }
+    }
+}
+
+/// Stores the objects of a storage [`Client`](super::Client), identified by their keys.
+///
+/// Every clone of a [`Client`](super::Client) shares the same [`Backend`].
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Deletes the object with the specified `key`, succeeding if the object does not exist.
+    async fn delete(&self, key: &str) -> Result<(), Error>;
+
+    /// Lists up to `limit` keys of the objects starting with `prefix`, following the
+    /// `continuation` of the previous [`Page`], if any.
+    async fn list(
+        &self,
+        continuation: Option<&str>,
+        limit: usize,
+        prefix: &str,
+    ) -> Result<Page, Error>;
+
+    /// Retrieves the contents and metadata of the object with the specified `key`.
+    ///
+    /// Returns [`Error::NotFound`] if the object does not exist.
+    async fn retrieve(&self, key: &str) -> Result<Object, Error>;
+
+    /// Retrieves the metadata of the object with the specified `key` without its contents.
+    ///
+    /// Returns [`Error::NotFound`] if the object does not exist. By default, the whole object is
+    /// retrieved to take its metadata.
+    async fn retrieve_metadata(&self, key: &str) -> Result<Metadata, Error> {
+        Ok(self.retrieve(key).await?.metadata)
+    }
+
+    /// Retrieves the metadata of the object with the specified `key` and streams its contents,
+    /// limited to the `range`, if any.
+    ///
+    /// Returns [`Error::NotFound`] if the object does not exist. By default, the whole object is
+    /// retrieved before streaming it.
+    async fn retrieve_stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<ObjectStream, Error> {
+        let object = self.retrieve(key).await?;
+        let content = match range {
+            Some(range) => Bytes::copy_from_slice(range.slice(&object.content)),
+            None => Bytes::from(object.content),
+        };
+        Ok(ObjectStream {
+            content: content_stream(content),
+            metadata: object.metadata,
+        })
+    }
+
+    /// Stores the `content` and `metadata` as the object with the specified `key`, replacing it if
+    /// it exists.
+    async fn upload(&self, content: &[u8], key: &str, metadata: &Metadata) -> Result<(), Error>;
+
+    /// Stores the `content` and `metadata` as the object with the specified `key` only if it meets
+    /// the `precondition`, atomically, returning the entity tag of the stored object.
+    ///
+    /// Returns [`Error::PreconditionFailed`] if the object does not meet the precondition, e.g.,
+    /// because it was replaced since it was retrieved.
+    async fn upload_if(
+        &self,
+        content: &[u8],
+        key: &str,
+        metadata: &Metadata,
+        precondition: &Precondition,
+    ) -> Result<String, Error>;
+
+    /// Stores the streamed `content` and the `metadata` as the object with the specified `key`,
+    /// replacing it if it exists.
+    ///
+    /// By default, the whole `content` is collected before storing it.
+    async fn upload_stream(
+        &self,
+        content: ContentStream,
+        key: &str,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let content: Vec<Bytes> = content.try_collect().await?;
+        self.upload(&content.concat(), key, metadata).await
+    }
+}
+
+/// A [`Backend`] that stores the objects as files within a local directory, where the keys are
+/// relative paths.
+///
+/// The metadata of each object is stored as a JSON file with the same path within the `.metadata`
+/// subdirectory.
+pub struct LocalBackend {
+    directory: PathBuf,
+}
+
+impl LocalBackend {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    /// Returns the path of the file of the object with the specified `key`, rejecting the keys
+    /// that would escape the directory.
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let relative_path = Path::new(key);
+        if key.is_empty()
+            || relative_path.starts_with(METADATA_DIRECTORY)
+            || !relative_path
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(Error::InvalidKey(String::from(key)));
+        }
+        Ok(self.directory.join(relative_path))
+    }
+
+    /// Returns the keys of every object stored within the directory, skipping the metadata files.
+    async fn keys(&self) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut directories = vec![(self.directory.clone(), String::new())];
+        while let Some((directory, prefix)) = directories.pop() {
+            let mut entries = match fs::read_dir(&directory).await {
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                result => result?,
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                let key = format!("{prefix}{name}");
+                if entry.file_type().await?.is_dir() {
+                    if key != METADATA_DIRECTORY {
+                        directories.push((entry.path(), format!("{key}/")));
+                    }
+                } else {
+                    keys.push(key);
+                }
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Creates the lock file held during the conditional uploads of the object with the specified
+    /// `key`, returning its path.
+    ///
+    /// A lock file older than [`LOCK_TIMEOUT`] is stale and replaced, while a recent one means that
+    /// another conditional upload is in progress and returns [`Error::PreconditionFailed`].
+    async fn lock(&self, key: &str) -> Result<PathBuf, Error> {
+        self.path(key)?;
+        let lock_path = self
+            .directory
+            .join(METADATA_DIRECTORY)
+            .join(format!("{key}.lock"));
+        if let Some(parent) = lock_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        for _ in 0..2 {
+            match fs::OpenOptions::new()
+                .create_new(true)
+                .write(true)
+                .open(&lock_path)
+                .await
+            {
+                Err(error) if error.kind() == ErrorKind::AlreadyExists => {}
+                result => {
+                    result?;
+                    return Ok(lock_path);
+                }
+            }
+            let is_stale = match fs::metadata(&lock_path).await {
+                Err(error) if error.kind() == ErrorKind::NotFound => continue,
+                result => result?
+                    .modified()?
+                    .elapsed()
+                    .is_ok_and(|age| age >= LOCK_TIMEOUT),
+            };
+            if !is_stale {
+                break;
+            }
+            match fs::remove_file(&lock_path).await {
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                result => result?,
+            }
+        }
+        Err(Error::PreconditionFailed(String::from(key)))
+    }
+
+    /// Returns the path of the metadata file of the object with the specified `key`.
+    fn metadata_path(&self, key: &str) -> Result<PathBuf, Error> {
+        self.path(key)?;
+        Ok(self
+            .directory
+            .join(METADATA_DIRECTORY)
+            .join(format!("{key}.json")))
+    }
+
+    /// Reads the metadata of the object with the specified `key`, which is empty if it was never
+    /// written.
+    async fn read_metadata(&self, key: &str) -> Result<Metadata, Error> {
+        match fs::read(self.metadata_path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Metadata::new()),
+            result => Ok(serde_json::from_slice(&result?)?),
+        }
+    }
+
+    /// Writes the metadata of the object with the specified `key`.
+    async fn write_metadata(&self, key: &str, metadata: &Metadata) -> Result<(), Error> {
+        let path = self.metadata_path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        Ok(fs::write(path, serde_json::to_vec(metadata)?).await?)
+    }
+}
+
+#[async_trait]
+impl Backend for LocalBackend {
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        match fs::remove_file(self.path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => {}
+            result => result?,
+        }
+        match fs::remove_file(self.metadata_path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(()),
+            result => Ok(result?),
+        }
+    }
+
+    async fn list(
+        &self,
+        continuation: Option<&str>,
+        limit: usize,
+        prefix: &str,
+    ) -> Result<Page, Error> {
+        Ok(Page::from_keys(
+            continuation,
+            self.keys().await?,
+            limit,
+            prefix,
+        ))
+    }
+
+    async fn retrieve(&self, key: &str) -> Result<Object, Error> {
+        let content = match fs::read(self.path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(Error::NotFound(String::from(key)))
+            }
+            result => result?,
+        };
+        let metadata = self.read_metadata(key).await?;
+        Ok(Object {
+            tag: Some(checksum(&content)),
+            content,
+            metadata,
+        })
+    }
+
+    async fn retrieve_metadata(&self, key: &str) -> Result<Metadata, Error> {
+        match fs::metadata(self.path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(Error::NotFound(String::from(key)))
+            }
+            result => result?,
+        };
+        self.read_metadata(key).await
+    }
+
+    async fn retrieve_stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<ObjectStream, Error> {
+        let mut file = match fs::File::open(self.path(key)?).await {
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                return Err(Error::NotFound(String::from(key)))
+            }
+            result => result?,
+        };
+        let metadata = self.read_metadata(key).await?;
+        let content: ContentStream = match range {
+            Some(range) => {
+                file.seek(SeekFrom::Start(range.start)).await?;
+                let file = file.take(range.length().unwrap_or(u64::MAX));
+                Box::pin(ReaderStream::new(file).map_err(Error::from))
+            }
+            None => Box::pin(ReaderStream::new(file).map_err(Error::from)),
+        };
+        Ok(ObjectStream { content, metadata })
+    }
+
+    async fn upload(&self, content: &[u8], key: &str, metadata: &Metadata) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, content).await?;
+        self.write_metadata(key, metadata).await
+    }
+
+    /// Holds a lock file while checking the `precondition` and uploading the object, so concurrent
+    /// conditional uploads of the object fail instead of waiting for the lock.
+    ///
+    /// A lock file left behind by a process that crashed during the upload is replaced once it is
+    /// older than [`LOCK_TIMEOUT`].
+    async fn upload_if(
+        &self,
+        content: &[u8],
+        key: &str,
+        metadata: &Metadata,
+        precondition: &Precondition,
+    ) -> Result<String, Error> {
+        let lock_path = self.lock(key).await?;
+        let result = async {
+            let tag = match fs::read(self.path(key)?).await {
+                Err(error) if error.kind() == ErrorKind::NotFound => None,
+                result => Some(checksum(&result?)),
+            };
+            if !precondition.is_met(tag.as_deref()) {
+                return Err(Error::PreconditionFailed(String::from(key)));
+            }
+            self.upload(content, key, metadata).await?;
+            Ok(checksum(content))
+        }
+        .await;
+        fs::remove_file(lock_path).await?;
+        result
+    }
+
+    async fn upload_stream(
+        &self,
+        mut content: ContentStream,
+        key: &str,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let mut file = fs::File::create(path).await?;
+        while let Some(chunk) = content.next().await {
+            file.write_all(&chunk?).await?;
+        }
+        file.flush().await?;
+        self.write_metadata(key, metadata).await
+    }
+}
+
+/// A [`Backend`] that keeps the objects in memory, meant for tests and local development.
+///
+/// Every clone of a [`MemoryBackend`] shares the same objects.
+#[derive(Clone, Default)]
+pub struct MemoryBackend {
+    objects: Arc<Mutex<HashMap<String, Object>>>,
+}
+
+#[async_trait]
+impl Backend for MemoryBackend {
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.objects.lock().unwrap().remove(key);
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        continuation: Option<&str>,
+        limit: usize,
+        prefix: &str,
+    ) -> Result<Page, Error> {
+        let keys: Vec<String> = self.objects.lock().unwrap().keys().cloned().collect();
+        Ok(Page::from_keys(continuation, keys, limit, prefix))
+    }
+
+    async fn retrieve(&self, key: &str) -> Result<Object, Error> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(String::from(key)))
+    }
+
+    async fn retrieve_metadata(&self, key: &str) -> Result<Metadata, Error> {
+        self.objects
+            .lock()
+            .unwrap()
+            .get(key)
+            .map(|object| object.metadata.clone())
+            .ok_or_else(|| Error::NotFound(String::from(key)))
+    }
+
+    async fn upload(&self, content: &[u8], key: &str, metadata: &Metadata) -> Result<(), Error> {
+        self.objects.lock().unwrap().insert(
+            String::from(key),
+            Object {
+                content: content.to_vec(),
+                metadata: metadata.clone(),
+                tag: Some(checksum(content)),
+            },
+        );
+        Ok(())
+    }
+
+    async fn upload_if(
+        &self,
+        content: &[u8],
+        key: &str,
+        metadata: &Metadata,
+        precondition: &Precondition,
+    ) -> Result<String, Error> {
+        let mut objects = self.objects.lock().unwrap();
+        let tag = objects.get(key).and_then(|object| object.tag.as_deref());
+        if !precondition.is_met(tag) {
+            return Err(Error::PreconditionFailed(String::from(key)));
+        }
+        let tag = checksum(content);
+        objects.insert(
+            String::from(key),
+            Object {
+                content: content.to_vec(),
+                metadata: metadata.clone(),
+                tag: Some(tag.clone()),
+            },
+        );
+        Ok(tag)
+    }
+}
+
+/// A [`Backend`] that stores the objects in an AWS S3 bucket.
+pub struct S3Backend {
+    /// The name of the AWS S3 bucket that the backend interacts with.
+    bucket: String,
+    /// An instance of [`Client`](`aws_sdk_s3::Client`) that provides access to the AWS S3 API.
+    client: aws_sdk_s3::Client,
+}
+
+impl S3Backend {
+    pub fn new(bucket: impl Into<String>, client: aws_sdk_s3::Client) -> Self {
+        Self {
+            bucket: bucket.into(),
+            client,
+        }
+    }
+
+    /// Creates a new [`S3Backend`] for the `bucket` with the credentials loaded from the
+    /// environment.
+    pub async fn from_environment(bucket: impl Into<String>) -> Self {
+        Self::new(bucket, aws_sdk_s3::Client::new(&from_env().load().await))
+    }
+
+    /// Uploads the parts of the multipart upload with the specified `upload_id`, starting with the
+    /// already collected `part`, and completes it.
+    async fn upload_parts(
+        &self,
+        mut content: ContentStream,
+        key: &str,
+        mut part: Vec<u8>,
+        upload_id: &str,
+    ) -> Result<(), Error> {
+        let mut completed_parts = Vec::new();
+        loop {
+            let chunk = content.next().await.transpose()?;
+            if let Some(chunk) = &chunk {
+                part.extend_from_slice(chunk);
+                if part.len() < PART_SIZE {
+                    continue;
+                }
+            }
+            if !part.is_empty() {
+                let part_number = completed_parts.len() as i32 + 1;
+                let output = self
+                    .client
+                    .upload_part()
+                    .bucket(&self.bucket)
+                    .key(key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(std::mem::take(&mut part)))
+                    .send()
+                    .await?;
+                completed_parts.push(
+                    CompletedPart::builder()
+                        .set_e_tag(output.e_tag().map(String::from))
+                        .part_number(part_number)
+                        .build(),
+                );
+            }
+            if chunk.is_none() {
+                break;
+            }
+        }
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(completed_parts))
+                    .build(),
+            )
+            .send()
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backend for S3Backend {
+    async fn delete(&self, key: &str) -> Result<(), Error> {
+        self.client
+            .delete_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    async fn list(
+        &self,
+        continuation: Option<&str>,
+        limit: usize,
+        prefix: &str,
+    ) -> Result<Page, Error> {
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .set_continuation_token(continuation.map(String::from))
+            .max_keys(limit as i32)
+            .prefix(prefix)
+            .send()
+            .await?;
+        Ok(Page {
+            continuation: output.next_continuation_token().map(String::from),
+            keys: output
+                .contents()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|object| object.key().map(String::from))
+                .collect(),
+        })
+    }
+
+    async fn retrieve(&self, key: &str) -> Result<Object, Error> {
+        let mut object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|error| Error::from_get_object(error, key))?;
+        Ok(Object {
+            metadata: object.metadata.take().unwrap_or_default(),
+            tag: object.e_tag.take(),
+            content: object.body.collect().await?.into_bytes().to_vec(),
+        })
+    }
+
+    async fn retrieve_metadata(&self, key: &str) -> Result<Metadata, Error> {
+        let mut output = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|error| Error::from_head_object(error, key))?;
+        Ok(output.metadata.take().unwrap_or_default())
+    }
+
+    async fn retrieve_stream(
+        &self,
+        key: &str,
+        range: Option<ByteRange>,
+    ) -> Result<ObjectStream, Error> {
+        let mut object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_range(range.map(|range| range.header()))
+            .send()
+            .await
+            .map_err(|error| Error::from_get_object(error, key))?;
+        Ok(ObjectStream {
+            metadata: object.metadata.take().unwrap_or_default(),
+            content: Box::pin(object.body.map_err(Error::from)),
+        })
+    }
+
+    async fn upload(&self, content: &[u8], key: &str, metadata: &Metadata) -> Result<(), Error> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_metadata(Some(metadata.clone()))
+            .body(ByteStream::new(content.into()))
+            .send()
+            .await?;
+        Ok(())
+    }
+
+    /// Sends the `precondition` as the `If-Match` or `If-None-Match` header of the upload, which
+    /// AWS S3 rejects if the object does not meet it.
+    async fn upload_if(
+        &self,
+        content: &[u8],
+        key: &str,
+        metadata: &Metadata,
+        precondition: &Precondition,
+    ) -> Result<String, Error> {
+        let (name, value) = match precondition {
+            Precondition::Absent => (IF_NONE_MATCH, HeaderValue::from_static("*")),
+            Precondition::Tag(tag) => (
+                IF_MATCH,
+                HeaderValue::from_str(tag)
+                    .map_err(|_| Error::PreconditionFailed(String::from(key)))?,
+            ),
+        };
+        let output = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_metadata(Some(metadata.clone()))
+            .body(ByteStream::new(content.into()))
+            .customize()
+            .await?
+            .mutate_request(|request| {
+                request.headers_mut().insert(name, value);
+            })
+            .send()
+            .await;
+        match output {
+            Ok(output) => Ok(output.e_tag().unwrap_or_default().to_owned()),
+            Err(error)
+                if matches!(
+                    error
+                        .raw_response()
+                        .map(|response| response.http().status()),
+                    Some(StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED)
+                ) =>
+            {
+                Err(Error::PreconditionFailed(String::from(key)))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Uploads the objects larger than a part through a multipart upload, aborting it if any part
+    /// fails to upload.
+    async fn upload_stream(
+        &self,
+        mut content: ContentStream,
+        key: &str,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let mut part = Vec::new();
+        while part.len() < PART_SIZE {
+            match content.next().await {
+                Some(chunk) => part.extend_from_slice(&chunk?),
+                None => return self.upload(&part, key, metadata).await,
+            }
+        }
+        let output = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .set_metadata(Some(metadata.clone()))
+            .send()
+            .await?;
+        let upload_id = output.upload_id().unwrap_or_default();
+        let result = self.upload_parts(content, key, part, upload_id).await;
+        if result.is_err() {
+            self.client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .send()
+                .await
+                .ok();
+        }
+        result
+    }
+}
@@ -0,0 +1,194 @@
+use super::{backend::ContentStream, Error};
+use bytes::Bytes;
+use flate2::{read::GzDecoder, write::GzEncoder};
+use futures::{stream, StreamExt};
+use std::{
+    io::{self, Read, Write},
+    mem,
+};
+
+/// The compression level of zstd, which favors the speed of the compression over its ratio.
+const ZSTD_LEVEL: i32 = 3;
+
+/// The encodings the objects can be compressed with, recorded as the content encoding metadata of
+/// the objects.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Returns the [`Compression`] with the specified content encoding `name`.
+    ///
+    /// Returns [`Error::UnknownCompression`] if the encoding is not supported.
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "gzip" => Ok(Self::Gzip),
+            "zstd" => Ok(Self::Zstd),
+            _ => Err(Error::UnknownCompression(String::from(name))),
+        }
+    }
+
+    /// Compresses the `content`.
+    pub fn compress(self, content: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(match self {
+            Self::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(content)?;
+                encoder.finish()?
+            }
+            Self::Zstd => zstd::encode_all(content, ZSTD_LEVEL)?,
+        })
+    }
+
+    /// Compresses the chunks of the `content` as they are streamed.
+    pub(super) fn compress_stream(self, content: ContentStream) -> Result<ContentStream, Error> {
+        let codec = match self {
+            Self::Gzip => {
+                StreamCodec::GzipEncoder(GzEncoder::new(Vec::new(), flate2::Compression::default()))
+            }
+            Self::Zstd => {
+                StreamCodec::ZstdEncoder(zstd::stream::write::Encoder::new(Vec::new(), ZSTD_LEVEL)?)
+            }
+        };
+        Ok(codec.transform(content))
+    }
+
+    /// Decompresses the `content`.
+    ///
+    /// Returns an error if the `content` was not compressed with this encoding.
+    pub fn decompress(self, content: &[u8]) -> Result<Vec<u8>, Error> {
+        Ok(match self {
+            Self::Gzip => {
+                let mut decompressed = Vec::new();
+                GzDecoder::new(content).read_to_end(&mut decompressed)?;
+                decompressed
+            }
+            Self::Zstd => zstd::decode_all(content)?,
+        })
+    }
+
+    /// Decompresses the chunks of the `content` as they are streamed.
+    pub(super) fn decompress_stream(self, content: ContentStream) -> Result<ContentStream, Error> {
+        let codec = match self {
+            Self::Gzip => StreamCodec::GzipDecoder(flate2::write::GzDecoder::new(Vec::new())),
+            Self::Zstd => StreamCodec::ZstdDecoder(zstd::stream::write::Decoder::new(Vec::new())?),
+        };
+        Ok(codec.transform(content))
+    }
+
+    /// Returns the content encoding name of the [`Compression`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// Compresses or decompresses the chunks of a stream incrementally, buffering only the output that
+/// has not been streamed yet.
+enum StreamCodec {
+    GzipDecoder(flate2::write::GzDecoder<Vec<u8>>),
+    GzipEncoder(GzEncoder<Vec<u8>>),
+    ZstdDecoder(zstd::stream::write::Decoder<'static, Vec<u8>>),
+    ZstdEncoder(zstd::stream::write::Encoder<'static, Vec<u8>>),
+}
+
+impl StreamCodec {
+    /// Writes the remaining output after the whole input was written.
+    fn finish(self) -> io::Result<Bytes> {
+        Ok(Bytes::from(match self {
+            Self::GzipDecoder(decoder) => decoder.finish()?,
+            Self::GzipEncoder(encoder) => encoder.finish()?,
+            Self::ZstdDecoder(mut decoder) => {
+                decoder.flush()?;
+                decoder.into_inner()
+            }
+            Self::ZstdEncoder(encoder) => encoder.finish()?,
+        }))
+    }
+
+    /// Maps the chunks of the `content` to their output.
+    fn transform(self, content: ContentStream) -> ContentStream {
+        Box::pin(stream::unfold(Some((content, self)), |state| async move {
+            let (mut content, mut codec) = state?;
+            loop {
+                return match content.next().await {
+                    Some(Ok(chunk)) => match codec.write(&chunk) {
+                        Ok(output) if output.is_empty() => continue,
+                        Ok(output) => Some((Ok(output), Some((content, codec)))),
+                        Err(error) => Some((Err(error.into()), None)),
+                    },
+                    Some(Err(error)) => Some((Err(error), None)),
+                    None => Some((codec.finish().map_err(Error::from), None)),
+                };
+            }
+        }))
+    }
+
+    /// Writes the `chunk` of the input, returning the output produced so far.
+    fn write(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        let output = match self {
+            Self::GzipDecoder(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.get_mut()
+            }
+            Self::GzipEncoder(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+            Self::ZstdDecoder(decoder) => {
+                decoder.write_all(chunk)?;
+                decoder.get_mut()
+            }
+            Self::ZstdEncoder(encoder) => {
+                encoder.write_all(chunk)?;
+                encoder.get_mut()
+            }
+        };
+        Ok(Bytes::from(mem::take(output)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Compression;
+    use bytes::Bytes;
+    use futures::{stream, TryStreamExt};
+    use std::{error::Error, fs};
+
+    #[test]
+    fn test_compression() -> Result<(), Box<dyn Error>> {
+        let file_content = fs::read("./test_files/test.osu")?;
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let compressed = compression.compress(&file_content)?;
+            assert!(compressed.len() < file_content.len());
+            assert_eq!(compression.decompress(&compressed)?, file_content);
+            assert!(compression.decompress(&file_content).is_err());
+            assert_eq!(Compression::from_name(compression.name())?, compression);
+        }
+        assert!(Compression::from_name("br").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compression_stream() -> Result<(), Box<dyn Error>> {
+        let file_content = fs::read("./test_files/test.osu")?;
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let chunks: Vec<_> = file_content
+                .chunks(100)
+                .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+                .collect();
+            let compressed = compression.compress_stream(Box::pin(stream::iter(chunks)))?;
+            let compressed: Vec<Bytes> = compressed.try_collect().await?;
+            assert_eq!(compression.decompress(&compressed.concat())?, file_content);
+            let decompressed = compression
+                .decompress_stream(Box::pin(stream::iter(compressed.into_iter().map(Ok))))?;
+            let decompressed: Vec<Bytes> = decompressed.try_collect().await?;
+            assert_eq!(decompressed.concat(), file_content);
+        }
+        Ok(())
+    }
+}
@@ -1,23 +1,55 @@
 use aws_sdk_s3::{
+    operation::complete_multipart_upload::CompleteMultipartUploadError,
+    operation::create_multipart_upload::CreateMultipartUploadError,
     operation::delete_object::DeleteObjectError, operation::get_object::GetObjectError,
-    operation::put_object::PutObjectError,
+    operation::head_object::HeadObjectError, operation::list_objects_v2::ListObjectsV2Error,
+    operation::put_object::PutObjectError, operation::upload_part::UploadPartError,
 };
 use aws_smithy_http::{byte_stream, result::SdkError};
-use std::{env::VarError, error, fmt};
+use http::StatusCode;
+use std::{env::VarError, error, fmt, io};
 
 /// Represents the different types of errors that can occur while using the `Client`.
 #[derive(Debug)]
 pub enum Error {
     /// An error related to creating a `ByteStream` for a request body.
     ByteStream(byte_stream::error::Error),
+    /// An object whose MD5 checksum differs from the expected one.
+    ChecksumMismatch {
+        actual: String,
+        expected: String,
+        key: String,
+    },
+    /// A stored crawl cursor that cannot be parsed.
+    CorruptCursor {
+        error: serde_json::Error,
+        name: String,
+    },
     /// An error related to deleting an object from the S3 bucket.
     DeleteObject(String),
     /// An error related to retrieving an object from the S3 bucket.
     GetObject(String),
+    /// An error related to reading or writing an object in a local directory.
+    IO(io::Error),
+    /// A key that does not identify a valid object, e.g., because it escapes the local directory.
+    InvalidKey(String),
+    /// An error related to listing the objects of the S3 bucket.
+    ListObjects(String),
+    /// An error related to uploading an object in parts to the S3 bucket.
+    MultipartUpload(String),
+    /// An object that does not exist.
+    NotFound(String),
+    /// An object that does not meet the precondition of a conditional upload, e.g., because it was
+    /// replaced concurrently.
+    PreconditionFailed(String),
     /// An error related to uploading an object to the S3 bucket.
     PutObject(String),
     /// An error related to serializing or deserializing JSON data.
     SerdeJson(serde_json::Error),
+    /// A storage backend that is not supported.
+    UnknownBackend(String),
+    /// A compression encoding that is not supported.
+    UnknownCompression(String),
     /// An error related to accessing an environment variable.
     Var(VarError),
 }
@@ -28,21 +60,96 @@ impl fmt::Display for Error {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::ByteStream(byte_stream) => byte_stream.fmt(formatter),
+            Self::ChecksumMismatch {
+                actual,
+                expected,
+                key,
+            } => write!(
+                formatter,
+                "Storage object {key} has checksum {actual} instead of {expected}"
+            ),
+            Self::CorruptCursor { error, name } => {
+                write!(formatter, "Storage cursor {name} is corrupt: {error}")
+            }
             Self::DeleteObject(delete_object) => delete_object.fmt(formatter),
             Self::GetObject(get_object) => get_object.fmt(formatter),
+            Self::IO(io) => io.fmt(formatter),
+            Self::InvalidKey(key) => write!(formatter, "Invalid storage key {key}"),
+            Self::ListObjects(list_objects) => list_objects.fmt(formatter),
+            Self::MultipartUpload(multipart_upload) => multipart_upload.fmt(formatter),
+            Self::NotFound(key) => write!(formatter, "Storage object {key} not found"),
+            Self::PreconditionFailed(key) => {
+                write!(formatter, "Storage object {key} was modified concurrently")
+            }
             Self::PutObject(put_object) => put_object.fmt(formatter),
             Self::SerdeJson(serde_json) => serde_json.fmt(formatter),
+            Self::UnknownBackend(backend) => write!(formatter, "Unknown storage backend {backend}"),
+            Self::UnknownCompression(compression) => {
+                write!(formatter, "Unknown storage compression {compression}")
+            }
             Self::Var(var) => var.fmt(formatter),
         }
     }
 }
 
+impl Error {
+    /// Converts an error retrieving the object with the specified `key` from the S3 bucket, mapping
+    /// a missing object to [`Error::NotFound`] as the other backends do.
+    pub(super) fn from_get_object(error: SdkError<GetObjectError>, key: &str) -> Self {
+        let is_missing = matches!(
+            &error,
+            SdkError::ServiceError(service_error) if service_error.err().is_no_such_key()
+        ) || matches!(
+            error
+                .raw_response()
+                .map(|response| response.http().status()),
+            Some(StatusCode::NOT_FOUND)
+        );
+        if is_missing {
+            Self::NotFound(String::from(key))
+        } else {
+            error.into()
+        }
+    }
+
+    /// Converts an error retrieving the metadata of the object with the specified `key` from the
+    /// S3 bucket, mapping a missing object to [`Error::NotFound`] as the other backends do.
+    pub(super) fn from_head_object(error: SdkError<HeadObjectError>, key: &str) -> Self {
+        let is_missing = matches!(
+            &error,
+            SdkError::ServiceError(service_error) if service_error.err().is_not_found()
+        ) || matches!(
+            error
+                .raw_response()
+                .map(|response| response.http().status()),
+            Some(StatusCode::NOT_FOUND)
+        );
+        if is_missing {
+            Self::NotFound(String::from(key))
+        } else {
+            Self::GetObject(error.to_string())
+        }
+    }
+}
+
 impl From<byte_stream::error::Error> for Error {
     fn from(error: byte_stream::error::Error) -> Self {
         Self::ByteStream(error)
     }
 }
 
+impl From<SdkError<CompleteMultipartUploadError>> for Error {
+    fn from(error: SdkError<CompleteMultipartUploadError>) -> Self {
+        Self::MultipartUpload(error.to_string())
+    }
+}
+
+impl From<SdkError<CreateMultipartUploadError>> for Error {
+    fn from(error: SdkError<CreateMultipartUploadError>) -> Self {
+        Self::MultipartUpload(error.to_string())
+    }
+}
+
 impl From<SdkError<DeleteObjectError>> for Error {
     fn from(error: SdkError<DeleteObjectError>) -> Self {
         Self::DeleteObject(error.to_string())
@@ -55,6 +162,18 @@ impl From<SdkError<GetObjectError>> for Error {
     }
 }
 
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::IO(error)
+    }
+}
+
+impl From<SdkError<ListObjectsV2Error>> for Error {
+    fn from(error: SdkError<ListObjectsV2Error>) -> Self {
+        Self::ListObjects(error.to_string())
+    }
+}
+
 impl From<SdkError<PutObjectError>> for Error {
     fn from(error: SdkError<PutObjectError>) -> Self {
         Self::PutObject(error.to_string())
@@ -67,6 +186,12 @@ impl From<serde_json::Error> for Error {
     }
 }
 
+impl From<SdkError<UploadPartError>> for Error {
+    fn from(error: SdkError<UploadPartError>) -> Self {
+        Self::MultipartUpload(error.to_string())
+    }
+}
+
 impl From<VarError> for Error {
     fn from(error: VarError) -> Self {
         Self::Var(error)
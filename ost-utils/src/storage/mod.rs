@@ -1,108 +1,685 @@
+pub mod backend;
+mod compression;
 mod error;
 pub mod models;
 
-use aws_config::from_env;
-use aws_smithy_http::byte_stream::ByteStream;
-use std::env;
+use self::backend::{
+    content_stream, Backend, ByteRange, ContentStream, LocalBackend, MemoryBackend, Metadata,
+    Object, ObjectStream, Page, Precondition, S3Backend,
+};
+pub use self::compression::Compression;
+use bytes::Bytes;
+use std::{env, sync::Arc};
 
 pub type Error = error::Error;
 
+/// The metadata name of the MD5 checksum of a beatmap file.
+pub const CHECKSUM_METADATA: &str = "md5";
+/// The metadata name of the [`Compression`] of an object, absent if the object is uncompressed.
+pub const CONTENT_ENCODING_METADATA: &str = "content-encoding";
+/// The largest number of keys listed per [`Page`], which is also the limit of AWS S3.
+pub const LIST_LIMIT: usize = 1_000;
+
+/// Returns the key of the file of the beatmap with the specified `id`.
+pub fn beatmap_key(id: i32) -> String {
+    format!("beatmaps/{id}.osu")
+}
+
+/// Computes the MD5 checksum of the `content` as a lowercase hexadecimal string, like the
+/// checksums of the osu! API.
+pub fn checksum(content: &[u8]) -> String {
+    format!("{:x}", md5::compute(content))
+}
+
+/// Verifies that the MD5 checksum of the `content` of the object with the specified `key` is the
+/// `expected` one, returning the checksum.
+pub fn verify_checksum(content: &[u8], expected: &str, key: &str) -> Result<String, Error> {
+    let actual = checksum(content);
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(Error::ChecksumMismatch {
+            actual,
+            expected: String::from(expected),
+            key: String::from(key),
+        });
+    }
+    Ok(actual)
+}
+
+/// Decompresses the contents of the `object` according to their recorded content encoding, if any,
+/// removing it from the metadata.
+fn decompress_object(mut object: Object) -> Result<Object, Error> {
+    if let Some(encoding) = object.metadata.remove(CONTENT_ENCODING_METADATA) {
+        object.content = Compression::from_name(&encoding)?.decompress(&object.content)?;
+    }
+    Ok(object)
+}
+
 #[derive(Clone)]
 pub struct Client {
-    /// The name of the AWS S3 bucket that the client interacts with.
-    bucket: String,
-    /// An instance of [`Client`](`aws_sdk_s3::Client`) that provides access to the AWS S3 API.
-    client: aws_sdk_s3::Client,
+    /// The [`Backend`] where the objects are stored, shared by every clone of the client.
+    backend: Arc<dyn Backend>,
+    /// The [`Compression`] of the uploaded objects, if any.
+    compression: Option<Compression>,
 }
 
 impl Client {
     /// Creates a new [`Client`] instance from environment variables.
     ///
-    /// This method retrieves the name of the AWS S3 bucket from the `AWS_BUCKET` environment variable and
-    /// creates a new [`Client`](`aws_sdk_s3::Client`) instance with the credentials loaded from the environment.
+    /// The backend is selected by the optional `STORAGE_BACKEND` environment variable:
+    /// - `s3` (the default) stores the objects in the AWS S3 bucket named by the `AWS_BUCKET`
+    ///   environment variable, with the credentials loaded from the environment.
+    /// - `local` stores the objects as files within the directory set by the `STORAGE_DIRECTORY`
+    ///   environment variable.
+    /// - `memory` keeps the objects in memory, so they are lost when the process exits.
     ///
-    /// Returns an error if either the `AWS_BUCKET` environment variable is not set or if the
-    /// [`Client`](`aws_sdk_s3::Client`) instance cannot be created due to missing or invalid credentials.
+    /// The uploaded objects are compressed with the optional `STORAGE_COMPRESSION` environment
+    /// variable, either `gzip` or `zstd`, and stored uncompressed if it is not set.
+    ///
+    /// Returns an error if the backend or the compression is unknown or if the environment
+    /// variables of the backend are not set.
     pub async fn from_environment() -> Result<Self, Error> {
-        Ok(Self {
-            bucket: env::var("AWS_BUCKET")?,
-            client: aws_sdk_s3::Client::new(&from_env().load().await),
-        })
+        let backend = env::var("STORAGE_BACKEND").unwrap_or_default();
+        let compression = match env::var("STORAGE_COMPRESSION").unwrap_or_default().as_str() {
+            "" => None,
+            name => Some(Compression::from_name(name)?),
+        };
+        let client = match backend.as_str() {
+            "" | "s3" => Self::new(S3Backend::from_environment(env::var("AWS_BUCKET")?).await),
+            "local" => Self::new(LocalBackend::new(env::var("STORAGE_DIRECTORY")?)),
+            "memory" => Self::new(MemoryBackend::default()),
+            _ => return Err(Error::UnknownBackend(backend)),
+        };
+        Ok(client.with_compression(compression))
     }
 
-    /// Deletes an object from the AWS S3 bucket with the specified `key`.
+    pub fn new(backend: impl Backend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+            compression: None,
+        }
+    }
+
+    /// Returns the `metadata` of an object uploaded by the client, with the content encoding of
+    /// its [`Compression`], if any.
+    fn encoded_metadata(&self, metadata: &Metadata) -> Metadata {
+        let mut metadata = metadata.clone();
+        metadata.remove(CONTENT_ENCODING_METADATA);
+        if let Some(compression) = self.compression {
+            metadata.insert(
+                String::from(CONTENT_ENCODING_METADATA),
+                String::from(compression.name()),
+            );
+        }
+        metadata
+    }
+
+    /// Compresses the objects uploaded by the client with the `compression`, if any.
     ///
-    /// Returns an error if the object cannot be deleted, e.g., because the bucket or the object does not exist.
+    /// The objects are decompressed on retrieval regardless of the compression of the client, so
+    /// previously uploaded objects remain readable.
+    pub fn with_compression(mut self, compression: Option<Compression>) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Deletes the object with the specified `key`, succeeding if the object does not exist.
+    ///
+    /// Returns an error if the object cannot be deleted.
     pub async fn delete(&self, key: impl Into<String>) -> Result<(), Error> {
-        self.client
-            .delete_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await?;
-        Ok(())
+        self.backend.delete(&key.into()).await
+    }
+
+    /// Lists the keys of the objects starting with `prefix`, up to [`LIST_LIMIT`] per [`Page`],
+    /// following the `continuation` of the previous page, if any.
+    pub async fn list(&self, continuation: Option<&str>, prefix: &str) -> Result<Page, Error> {
+        self.backend.list(continuation, LIST_LIMIT, prefix).await
     }
 
-    /// Retrieves an object from the AWS S3 bucket with the specified `key`.
+    /// Lists the keys of every object starting with `prefix`, going through every [`Page`].
+    pub async fn list_all(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut keys = Vec::new();
+        let mut continuation = None;
+        loop {
+            let page = self.list(continuation.as_deref(), prefix).await?;
+            keys.extend(page.keys);
+            if page.continuation.is_none() {
+                return Ok(keys);
+            }
+            continuation = page.continuation;
+        }
+    }
+
+    /// Retrieves the object with the specified `key`.
     ///
     /// Returns the contents of the object as a [`Vec<u8>`] or an error if the object cannot be retrieved,
-    /// e.g., because the bucket or the object does not exist.
+    /// e.g., because the object does not exist.
     pub async fn retrieve(&self, key: impl Into<String>) -> Result<Vec<u8>, Error> {
-        Ok(self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await?
-            .body
-            .collect()
-            .await?
-            .into_bytes()
-            .into_iter()
-            .collect())
-    }
-
-    /// Uploads the `content` data to the AWS S3 bucket,
-    /// with the specified `key` determining the location within the bucket where the `content` will be stored.
+        Ok(self.retrieve_object(key).await?.content)
+    }
+
+    /// Retrieves the file of the beatmap with the specified `id`, verifying its MD5 checksum
+    /// against the `checksum`, if any, or otherwise against the one recorded when it was uploaded.
+    ///
+    /// Returns [`Error::ChecksumMismatch`] if the file does not match the checksum, e.g., because it
+    /// is outdated or corrupted.
+    pub async fn retrieve_beatmap(
+        &self,
+        checksum: Option<&str>,
+        id: i32,
+    ) -> Result<Vec<u8>, Error> {
+        let key = beatmap_key(id);
+        let object = self.retrieve_object(key.as_str()).await?;
+        if let Some(expected) =
+            checksum.or(object.metadata.get(CHECKSUM_METADATA).map(String::as_str))
+        {
+            verify_checksum(&object.content, expected, &key)?;
+        }
+        Ok(object.content)
+    }
+
+    /// Retrieves the contents and metadata of the object with the specified `key`, decompressing
+    /// the contents according to their recorded content encoding.
+    pub async fn retrieve_object(&self, key: impl Into<String>) -> Result<Object, Error> {
+        decompress_object(self.backend.retrieve(&key.into()).await?)
+    }
+
+    /// Retrieves the metadata of the object with the specified `key` and streams its contents,
+    /// decompressed according to their recorded content encoding and limited to the `range`, if
+    /// any.
+    ///
+    /// The `range` of a compressed object refers to its decompressed contents, so the metadata is
+    /// retrieved first to request only the range of uncompressed objects, while compressed ones are
+    /// retrieved whole to decompress them before streaming the range.
+    pub async fn retrieve_stream(
+        &self,
+        key: impl Into<String>,
+        range: Option<ByteRange>,
+    ) -> Result<ObjectStream, Error> {
+        let key = key.into();
+        let Some(range) = range else {
+            let mut object = self.backend.retrieve_stream(&key, None).await?;
+            if let Some(encoding) = object.metadata.remove(CONTENT_ENCODING_METADATA) {
+                object.content =
+                    Compression::from_name(&encoding)?.decompress_stream(object.content)?;
+            }
+            return Ok(object);
+        };
+        let metadata = self.backend.retrieve_metadata(&key).await?;
+        if !metadata.contains_key(CONTENT_ENCODING_METADATA) {
+            let object = self.backend.retrieve_stream(&key, Some(range)).await?;
+            // The object may have been replaced by a compressed one since its metadata was read.
+            if !object.metadata.contains_key(CONTENT_ENCODING_METADATA) {
+                return Ok(object);
+            }
+        }
+        let object = self.retrieve_object(key).await?;
+        Ok(ObjectStream {
+            content: content_stream(Bytes::copy_from_slice(range.slice(&object.content))),
+            metadata: object.metadata,
+        })
+    }
+
+    /// Uploads the object with the specified `key` again if it is not compressed with the
+    /// [`Compression`] of the client, keeping its metadata.
+    ///
+    /// Returns whether the object was uploaded again, [`Error::NotFound`] if it does not exist, or
+    /// [`Error::PreconditionFailed`] if it was replaced while being recompressed.
+    pub async fn recompress(&self, key: impl Into<String>) -> Result<bool, Error> {
+        let key = key.into();
+        let object = self.backend.retrieve(&key).await?;
+        let encoding = object.metadata.get(CONTENT_ENCODING_METADATA);
+        if encoding.map(String::as_str) == self.compression.map(Compression::name) {
+            return Ok(false);
+        }
+        let precondition = Precondition::from_tag(object.tag.clone());
+        let object = decompress_object(object)?;
+        self.upload_object_if(&object.content, key, &object.metadata, &precondition)
+            .await?;
+        Ok(true)
+    }
+
+    /// Uploads the `content` data, with the specified `key` determining the location where the
+    /// `content` will be stored.
     ///
     /// If the upload is successful, returns `Result` with `Ok(())`.
     /// If the upload fails, returns `Result` with `Err` containing the error.
     pub async fn upload(&self, content: &[u8], key: impl Into<String>) -> Result<(), Error> {
-        self.client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .body(ByteStream::new(content.into()))
-            .send()
-            .await?;
-        Ok(())
+        self.upload_object(content, key, &Metadata::new()).await
+    }
+
+    /// Uploads the file of the beatmap with the specified `id`, recording its MD5 checksum as
+    /// metadata after verifying it against the `checksum`, if any.
+    ///
+    /// Returns [`Error::ChecksumMismatch`] without uploading the file if it does not match the
+    /// checksum, e.g., because the download was truncated or returned an error page.
+    pub async fn upload_beatmap(
+        &self,
+        checksum: Option<&str>,
+        content: &[u8],
+        id: i32,
+    ) -> Result<(), Error> {
+        let key = beatmap_key(id);
+        let checksum = match checksum {
+            Some(expected) => verify_checksum(content, expected, &key)?,
+            None => self::checksum(content),
+        };
+        let metadata = Metadata::from([(String::from(CHECKSUM_METADATA), checksum)]);
+        self.upload_object(content, key, &metadata).await
+    }
+
+    /// Uploads the `content` data along with its `metadata`, replacing the object with the
+    /// specified `key` if it exists.
+    ///
+    /// The `content` is compressed with the [`Compression`] of the client, if any, which is
+    /// recorded as its content encoding.
+    pub async fn upload_object(
+        &self,
+        content: &[u8],
+        key: impl Into<String>,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let metadata = self.encoded_metadata(metadata);
+        let Some(compression) = self.compression else {
+            return self.backend.upload(content, &key.into(), &metadata).await;
+        };
+        let content = compression.compress(content)?;
+        self.backend.upload(&content, &key.into(), &metadata).await
+    }
+
+    /// Uploads the `content` data along with its `metadata` only if the object with the specified
+    /// `key` meets the `precondition`, atomically, returning the entity tag of the uploaded object.
+    ///
+    /// Returns [`Error::PreconditionFailed`] if the object does not meet the precondition, e.g.,
+    /// because it was replaced since it was retrieved.
+    pub async fn upload_object_if(
+        &self,
+        content: &[u8],
+        key: impl Into<String>,
+        metadata: &Metadata,
+        precondition: &Precondition,
+    ) -> Result<String, Error> {
+        let metadata = self.encoded_metadata(metadata);
+        let content = match self.compression {
+            Some(compression) => compression.compress(content)?,
+            None => content.to_vec(),
+        };
+        self.backend
+            .upload_if(&content, &key.into(), &metadata, precondition)
+            .await
+    }
+
+    /// Uploads the streamed `content` along with its `metadata`, replacing the object with the
+    /// specified `key` if it exists.
+    ///
+    /// The `content` is compressed as it is streamed with the [`Compression`] of the client, if
+    /// any, and large objects are uploaded in parts when the backend supports it.
+    pub async fn upload_stream(
+        &self,
+        content: ContentStream,
+        key: impl Into<String>,
+        metadata: &Metadata,
+    ) -> Result<(), Error> {
+        let metadata = self.encoded_metadata(metadata);
+        let content = match self.compression {
+            Some(compression) => compression.compress_stream(content)?,
+            None => content,
+        };
+        self.backend
+            .upload_stream(content, &key.into(), &metadata)
+            .await
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::storage::Client;
+    use super::{
+        backend::{
+            Backend, ByteRange, LocalBackend, MemoryBackend, Metadata, Precondition, S3Backend,
+        },
+        beatmap_key, checksum, Client, Compression, Error as StorageError, CHECKSUM_METADATA,
+        CONTENT_ENCODING_METADATA,
+    };
+    use aws_sdk_s3::config::{Credentials, Region};
+    use bytes::Bytes;
     use dotenv::dotenv;
-    use std::{error::Error, fs};
+    use futures::{stream, TryStreamExt};
+    use std::{
+        env,
+        error::Error,
+        fs,
+        time::{Duration, SystemTime},
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
 
     const TEST_FILE: &str = "test.osu";
+    /// The response of AWS S3 to the retrieval of a missing object.
+    const NO_SUCH_KEY_RESPONSE: &str = "HTTP/1.1 404 Not Found\r\n\
+        Content-Type: application/xml\r\nContent-Length: 102\r\nConnection: close\r\n\r\n\
+        <?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <Error><Code>NoSuchKey</Code><Message>Missing</Message></Error>";
+    /// The response of AWS S3 to the deletion of an object, whether it exists or not.
+    const NO_CONTENT_RESPONSE: &str =
+        "HTTP/1.1 204 No Content\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+
+    /// Returns a [`Client`] of an [`S3Backend`] whose bucket is served by a local server without
+    /// any objects, which answers the requests as AWS S3 does for missing objects.
+    pub(super) async fn empty_s3_client() -> Result<Client, Box<dyn Error>> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 1024];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        match socket.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+                    let response = if request.starts_with(b"DELETE") {
+                        NO_CONTENT_RESPONSE
+                    } else {
+                        NO_SUCH_KEY_RESPONSE
+                    };
+                    let _ = socket.write_all(response.as_bytes()).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        let config = aws_config::from_env()
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(format!("http://{address}"))
+            .region(Region::new("us-east-1"))
+            .load()
+            .await;
+        let config = aws_sdk_s3::config::Builder::from(&config)
+            .force_path_style(true)
+            .build();
+        Ok(Client::new(S3Backend::new(
+            "bucket",
+            aws_sdk_s3::Client::from_conf(config),
+        )))
+    }
+
+    /// Verifies that every backend reports a missing object as [`StorageError::NotFound`], also
+    /// when recompressing it, and deletes it successfully.
+    async fn check_missing_object(client: &Client) -> Result<(), Box<dyn Error>> {
+        assert!(matches!(
+            client.retrieve(TEST_FILE).await,
+            Err(StorageError::NotFound(key)) if key == TEST_FILE
+        ));
+        for range in [None, Some(ByteRange::new(10, Some(19)))] {
+            assert!(matches!(
+                client.retrieve_stream(TEST_FILE, range).await,
+                Err(StorageError::NotFound(_))
+            ));
+        }
+        assert!(matches!(
+            client.recompress(TEST_FILE).await,
+            Err(StorageError::NotFound(_))
+        ));
+        client.delete(TEST_FILE).await?;
+        Ok(())
+    }
 
-    /// Test to verify the functionality of the [`Client`]'s `upload`, `retrieve`, and `delete` methods.
+    #[tokio::test]
+    async fn test_missing_object() -> Result<(), Box<dyn Error>> {
+        check_missing_object(&Client::new(MemoryBackend::default())).await?;
+        let directory = env::temp_dir().join(format!("ost-missing-{}", std::process::id()));
+        check_missing_object(&Client::new(LocalBackend::new(&directory))).await?;
+        check_missing_object(&empty_s3_client().await?).await
+    }
+
+    /// Verifies the functionality of the [`Client`]'s `upload`, `retrieve`, and `delete` methods.
     ///
-    /// The test reads a file `./test_files/test.osu`, uploads it to the S3 bucket using the `upload` method,
-    /// retrieves it using the `retrieve` method, and then deletes it using the `delete` method.
+    /// The file `./test_files/test.osu` is uploaded using the `upload` method, retrieved using the
+    /// `retrieve` method, and then deleted using the `delete` method.
     /// The contents of the file are verified to match the uploaded and retrieved data.
     /// The `retrieve` method is also used after the file has been deleted to ensure that it has been successfully deleted.
+    async fn check_client(client: &Client, key: &str) -> Result<(), Box<dyn Error>> {
+        let file_content = fs::read("./test_files/test.osu")?;
+        assert!(client.upload(&file_content, key).await.is_ok());
+        assert_eq!(client.retrieve(key).await?, file_content);
+        assert!(client.delete(key).await.is_ok());
+        assert!(client.retrieve(key).await.is_err());
+        Ok(())
+    }
+
     #[tokio::test]
+    #[ignore = "requires AWS credentials"]
     async fn test_storage() -> Result<(), Box<dyn Error>> {
         dotenv().ok();
-        let client = Client::from_environment().await?;
+        check_client(&Client::from_environment().await?, TEST_FILE).await
+    }
+
+    #[tokio::test]
+    async fn test_local_storage() -> Result<(), Box<dyn Error>> {
+        let directory = env::temp_dir().join(format!("ost-storage-{}", std::process::id()));
+        let client = Client::new(LocalBackend::new(&directory));
+        check_client(&client, &format!("beatmaps/{TEST_FILE}")).await?;
+        let file_content = fs::read("./test_files/test.osu")?;
+        client.upload_beatmap(None, &file_content, 1).await?;
+        assert_eq!(client.retrieve_beatmap(None, 1).await?, file_content);
+        assert_eq!(
+            client
+                .retrieve_object(beatmap_key(1))
+                .await?
+                .metadata
+                .get(CHECKSUM_METADATA),
+            Some(&checksum(&file_content))
+        );
+        client.upload(&file_content, TEST_FILE).await?;
+        assert_eq!(client.list_all("").await?, ["beatmaps/1.osu", TEST_FILE]);
+        assert_eq!(client.list_all("beatmaps/").await?, ["beatmaps/1.osu"]);
+        client.delete(TEST_FILE).await?;
+        let tag = client
+            .upload_object_if(b"a", TEST_FILE, &Metadata::new(), &Precondition::Absent)
+            .await?;
+        assert!(matches!(
+            client
+                .upload_object_if(b"b", TEST_FILE, &Metadata::new(), &Precondition::Absent)
+                .await,
+            Err(StorageError::PreconditionFailed(_))
+        ));
+        client
+            .upload_object_if(b"b", TEST_FILE, &Metadata::new(), &Precondition::Tag(tag))
+            .await?;
+        assert_eq!(client.retrieve(TEST_FILE).await?, b"b");
+        let lock_path = directory
+            .join(".metadata")
+            .join(format!("{TEST_FILE}.lock"));
+        let lock = fs::File::create(&lock_path)?;
+        let precondition = Precondition::Tag(checksum(b"b"));
+        assert!(matches!(
+            client
+                .upload_object_if(b"c", TEST_FILE, &Metadata::new(), &precondition)
+                .await,
+            Err(StorageError::PreconditionFailed(_))
+        ));
+        lock.set_modified(SystemTime::now() - Duration::from_secs(120))?;
+        client
+            .upload_object_if(b"c", TEST_FILE, &Metadata::new(), &precondition)
+            .await?;
+        assert!(!lock_path.exists());
+        assert_eq!(client.retrieve(TEST_FILE).await?, b"c");
+        client.delete(TEST_FILE).await?;
+        assert!(matches!(
+            client.retrieve(".metadata/beatmaps/1.osu.json").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        assert!(matches!(
+            client.retrieve("../test.osu").await,
+            Err(StorageError::InvalidKey(_))
+        ));
+        fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_beatmap_checksum() -> Result<(), Box<dyn Error>> {
+        let client = Client::new(MemoryBackend::default());
+        let file_content = fs::read("./test_files/test.osu")?;
+        let checksum = checksum(&file_content);
+        assert!(matches!(
+            client
+                .upload_beatmap(Some("0123456789abcdef0123456789abcdef"), &file_content, 1)
+                .await,
+            Err(StorageError::ChecksumMismatch { .. })
+        ));
+        assert!(client.retrieve_beatmap(None, 1).await.is_err());
+        client
+            .upload_beatmap(Some(&checksum), &file_content, 1)
+            .await?;
+        let object = client.retrieve_object(beatmap_key(1)).await?;
+        assert_eq!(object.metadata.get(CHECKSUM_METADATA), Some(&checksum));
+        assert_eq!(client.retrieve_beatmap(None, 1).await?, file_content);
+        assert_eq!(
+            client.retrieve_beatmap(Some(&checksum), 1).await?,
+            file_content
+        );
+        client
+            .upload_object(&file_content[1..], beatmap_key(1), &object.metadata)
+            .await?;
+        match client.retrieve_beatmap(None, 1).await {
+            Err(StorageError::ChecksumMismatch { expected, key, .. }) => {
+                assert_eq!(expected, checksum);
+                assert_eq!(key, "beatmaps/1.osu");
+            }
+            _ => panic!("expected a checksum mismatch"),
+        }
+        client.upload(&file_content[1..], beatmap_key(2)).await?;
+        assert_eq!(client.retrieve_beatmap(None, 2).await?, &file_content[1..]);
+        assert!(client.retrieve_beatmap(Some(&checksum), 2).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compressed_storage() -> Result<(), Box<dyn Error>> {
+        let backend = MemoryBackend::default();
+        let uncompressed = Client::new(backend.clone());
+        let file_content = fs::read("./test_files/test.osu")?;
+        uncompressed.upload_beatmap(None, &file_content, 1).await?;
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let client = Client::new(backend.clone()).with_compression(Some(compression));
+            check_client(&client, TEST_FILE).await?;
+            assert_eq!(client.retrieve_beatmap(None, 1).await?, file_content);
+            assert!(client.recompress(beatmap_key(1)).await?);
+            assert!(!client.recompress(beatmap_key(1)).await?);
+            let object = backend.retrieve(&beatmap_key(1)).await?;
+            assert!(object.content.len() < file_content.len());
+            assert_eq!(
+                object
+                    .metadata
+                    .get(CONTENT_ENCODING_METADATA)
+                    .map(String::as_str),
+                Some(compression.name())
+            );
+            assert_eq!(
+                object.metadata.get(CHECKSUM_METADATA),
+                Some(&checksum(&file_content))
+            );
+            assert_eq!(uncompressed.retrieve_beatmap(None, 1).await?, file_content);
+            let object = uncompressed.retrieve_object(beatmap_key(1)).await?;
+            assert!(!object.metadata.contains_key(CONTENT_ENCODING_METADATA));
+        }
+        assert!(uncompressed.recompress(beatmap_key(1)).await?);
+        assert_eq!(
+            backend.retrieve(&beatmap_key(1)).await?.content,
+            file_content
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list() -> Result<(), Box<dyn Error>> {
+        let backend = MemoryBackend::default();
+        let client = Client::new(backend.clone());
+        for id in [3, 1, 2] {
+            client.upload(b"", beatmap_key(id)).await?;
+        }
+        client.upload(b"", TEST_FILE).await?;
+        let page = backend.list(None, 2, "beatmaps/").await?;
+        assert_eq!(page.keys, ["beatmaps/1.osu", "beatmaps/2.osu"]);
+        let page = backend
+            .list(page.continuation.as_deref(), 2, "beatmaps/")
+            .await?;
+        assert_eq!(page.keys, ["beatmaps/3.osu"]);
+        assert!(page.continuation.is_none());
+        assert_eq!(client.list_all("beatmaps/").await?.len(), 3);
+        assert_eq!(client.list_all("").await?.len(), 4);
+        assert!(client.list_all("users/").await?.is_empty());
+        Ok(())
+    }
+
+    /// Verifies the functionality of the [`Client`]'s `upload_stream` and `retrieve_stream`
+    /// methods, including ranges, with the file `./test_files/test.osu` uploaded in chunks.
+    async fn check_stream(client: &Client, key: &str) -> Result<(), Box<dyn Error>> {
         let file_content = fs::read("./test_files/test.osu")?;
-        assert!(client.upload(&file_content, TEST_FILE).await.is_ok());
-        assert_eq!(client.retrieve(TEST_FILE).await?, file_content);
-        assert!(client.delete(TEST_FILE).await.is_ok());
-        assert!(client.retrieve(TEST_FILE).await.is_err());
+        let chunks: Vec<_> = file_content
+            .chunks(100)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect();
+        let metadata = Metadata::from([(String::from("name"), String::from(TEST_FILE))]);
+        client
+            .upload_stream(Box::pin(stream::iter(chunks)), key, &metadata)
+            .await?;
+        assert_eq!(client.retrieve(key).await?, file_content);
+        for (range, expected) in [
+            (None, &file_content[..]),
+            (Some(ByteRange::new(10, Some(19))), &file_content[10..20]),
+            (Some(ByteRange::new(100, None)), &file_content[100..]),
+        ] {
+            let object = client.retrieve_stream(key, range).await?;
+            assert_eq!(object.metadata, metadata);
+            let content: Vec<Bytes> = object.content.try_collect().await?;
+            assert_eq!(content.concat(), expected);
+        }
+        assert!(matches!(
+            client.retrieve_stream(TEST_FILE, None).await,
+            Err(StorageError::NotFound(_))
+        ));
+        client.delete(key).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream() -> Result<(), Box<dyn Error>> {
+        check_stream(&Client::new(MemoryBackend::default()), "beatmaps/1.osu").await?;
+        let directory = env::temp_dir().join(format!("ost-stream-{}", std::process::id()));
+        let client = Client::new(LocalBackend::new(&directory));
+        check_stream(&client, "beatmaps/1.osu").await?;
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let client = client.clone().with_compression(Some(compression));
+            check_stream(&client, "beatmaps/1.osu").await?;
+        }
+        fs::remove_dir_all(directory)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_range() {
+        let content = b"0123456789";
+        assert_eq!(ByteRange::new(2, Some(4)).slice(content), b"234");
+        assert_eq!(ByteRange::new(8, None).slice(content), b"89");
+        assert_eq!(ByteRange::new(8, Some(20)).slice(content), b"89");
+        assert!(ByteRange::new(20, None).slice(content).is_empty());
+        assert_eq!(ByteRange::new(2, Some(4)).header(), "bytes=2-4");
+        assert_eq!(ByteRange::new(2, None).header(), "bytes=2-");
+        assert_eq!(ByteRange::new(2, Some(4)).length(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_memory_storage() -> Result<(), Box<dyn Error>> {
+        let client = Client::new(MemoryBackend::default());
+        check_client(&client, TEST_FILE).await?;
+        assert!(matches!(
+            client.retrieve(TEST_FILE).await,
+            Err(StorageError::NotFound(_))
+        ));
         Ok(())
     }
 }
@@ -1,31 +1,268 @@
-use super::{Client, Error};
+use super::{
+    backend::{Metadata, Precondition},
+    Client, Error,
+};
+use base64::engine::{general_purpose::STANDARD, Engine as _};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-const CURSOR_FILE: &str = "cursor.json";
+/// The name of the [`Cursor`] of the crawl over the ranked osu!standard beatmapsets.
+pub const RANKED_CURSOR: &str = "ranked";
+/// The key of the single cursor stored before the cursors were named, read as the
+/// [`RANKED_CURSOR`] until it is first updated.
+const LEGACY_CURSOR_FILE: &str = "cursor.json";
 
+/// The position of a named crawl over the beatmapsets of the osu! API, e.g., over the ranked,
+/// loved or qualified beatmapsets of a mode.
+///
+/// Cursors are updated with compare-and-swap semantics, so a task can't overwrite the progress of
+/// another task that updated the same cursor since it was retrieved.
 #[derive(Deserialize, Serialize)]
 pub struct Cursor {
+    /// The ranked date in milliseconds of the last processed beatmapset, used to report progress.
     pub approved_date: i64,
-    pub id: i32,
+    /// The opaque osu! API cursor of the next page to process, or `None` to start over.
+    pub cursor_string: Option<String>,
+    /// The name of the cursor, which determines where it is stored.
+    #[serde(skip)]
+    name: String,
+    /// The entity tag of the stored cursor when it was retrieved or last updated, or `None` if it
+    /// was not stored.
+    #[serde(skip)]
+    tag: Option<String>,
+    /// The time of the last update of the cursor.
+    #[serde(default)]
+    pub updated_at: DateTime<Utc>,
+    /// The number of updates of the cursor.
+    #[serde(default)]
+    pub version: u64,
+}
+
+/// The format of the [`Cursor`] before it stored the opaque osu! API cursor.
+#[derive(Deserialize)]
+struct LegacyCursor {
+    approved_date: i64,
+    id: i32,
+}
+
+impl LegacyCursor {
+    fn into_cursor(self, name: &str) -> Cursor {
+        let cursor_string = STANDARD.encode(format!(
+            r#"{{"approved_date":"{}","id":"{}"}}"#,
+            self.approved_date, self.id
+        ));
+        Cursor::new(self.approved_date, Some(cursor_string), name)
+    }
 }
 
 impl Cursor {
-    pub fn new(approved_date: i64, id: i32) -> Self {
-        Self { approved_date, id }
+    pub fn new(approved_date: i64, cursor_string: Option<String>, name: impl Into<String>) -> Self {
+        Self {
+            approved_date,
+            cursor_string,
+            name: name.into(),
+            tag: None,
+            updated_at: DateTime::default(),
+            version: 0,
+        }
+    }
+
+    /// Returns the key where the cursor with the specified `name` is stored.
+    fn key(name: &str) -> String {
+        format!("cursors/{name}.json")
     }
 
-    pub async fn retrieve(file_storage: &Client) -> Result<Self, Error> {
-        if let Ok(file) = file_storage.retrieve(CURSOR_FILE).await {
-            Ok(serde_json::from_slice::<Self>(&file)?)
-        } else {
-            Ok(Self::new(0, 0))
+    /// Parses a stored [`Cursor`] with the specified `name`, converting it from the legacy format
+    /// if needed.
+    fn parse(file: &[u8], name: &str) -> Result<Self, Error> {
+        if let Ok(cursor) = serde_json::from_slice::<LegacyCursor>(file) {
+            return Ok(cursor.into_cursor(name));
         }
+        let mut cursor =
+            serde_json::from_slice::<Self>(file).map_err(|error| Error::CorruptCursor {
+                error,
+                name: String::from(name),
+            })?;
+        cursor.name = String::from(name);
+        Ok(cursor)
+    }
+
+    /// Retrieves the cursor with the specified `name`, starting over if it was never stored.
+    ///
+    /// Returns [`Error::CorruptCursor`] if the stored cursor cannot be parsed, unless `reset` is
+    /// set, in which case the crawl starts over and the corrupt cursor is replaced on the next
+    /// update.
+    pub async fn retrieve(file_storage: &Client, name: &str, reset: bool) -> Result<Self, Error> {
+        let object = match file_storage.retrieve_object(Self::key(name)).await {
+            Err(Error::NotFound(_)) if name == RANKED_CURSOR => {
+                return match file_storage.retrieve(LEGACY_CURSOR_FILE).await {
+                    Ok(file) => Self::parse(&file, name),
+                    Err(Error::NotFound(_)) => Ok(Self::new(0, None, name)),
+                    Err(error) => Err(error),
+                }
+                .or_else(|error| match error {
+                    Error::CorruptCursor { .. } if reset => Ok(Self::new(0, None, name)),
+                    error => Err(error),
+                });
+            }
+            Err(Error::NotFound(_)) => return Ok(Self::new(0, None, name)),
+            result => result?,
+        };
+        let mut cursor = match Self::parse(&object.content, name) {
+            Err(Error::CorruptCursor { .. }) if reset => Self::new(0, None, name),
+            result => result?,
+        };
+        cursor.tag = object.tag;
+        Ok(cursor)
     }
 
-    pub async fn update(&self, file_storage: &Client) -> Result<(), Error> {
-        file_storage
-            .upload(&serde_json::to_vec(self)?, CURSOR_FILE)
+    /// Stores the cursor, incrementing its version, only if the stored cursor was not updated since
+    /// this one was retrieved or last updated.
+    ///
+    /// Returns [`Error::PreconditionFailed`] if the stored cursor was updated by another task.
+    pub async fn update(&mut self, file_storage: &Client) -> Result<(), Error> {
+        let precondition = Precondition::from_tag(self.tag.clone());
+        self.updated_at = Utc::now();
+        self.version += 1;
+        let result = file_storage
+            .upload_object_if(
+                &serde_json::to_vec(self)?,
+                Self::key(&self.name),
+                &Metadata::new(),
+                &precondition,
+            )
+            .await;
+        match result {
+            Ok(tag) => {
+                self.tag = Some(tag);
+                Ok(())
+            }
+            Err(error) => {
+                self.version -= 1;
+                Err(error)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cursor, LEGACY_CURSOR_FILE, RANKED_CURSOR};
+    use crate::storage::{
+        backend::MemoryBackend, tests::empty_s3_client, Client, Error as StorageError,
+    };
+    use std::error::Error;
+
+    #[tokio::test]
+    async fn test_cursor() -> Result<(), Box<dyn Error>> {
+        let storage = Client::new(MemoryBackend::default());
+        let mut cursor = Cursor::retrieve(&storage, RANKED_CURSOR, false).await?;
+        assert_eq!(cursor.approved_date, 0);
+        assert!(cursor.cursor_string.is_none());
+        assert_eq!(cursor.version, 0);
+        cursor.approved_date = 1;
+        cursor.cursor_string = Some(String::from("cursor"));
+        cursor.update(&storage).await?;
+        let mut stored = Cursor::retrieve(&storage, RANKED_CURSOR, false).await?;
+        assert_eq!(stored.approved_date, 1);
+        assert_eq!(stored.cursor_string.as_deref(), Some("cursor"));
+        assert_eq!(stored.version, 1);
+        assert_eq!(stored.updated_at, cursor.updated_at);
+        let loved = Cursor::retrieve(&storage, "loved", false).await?;
+        assert_eq!(loved.approved_date, 0);
+        stored.update(&storage).await?;
+        assert!(matches!(
+            cursor.update(&storage).await,
+            Err(StorageError::PreconditionFailed(_))
+        ));
+        assert_eq!(cursor.version, 1);
+        stored.update(&storage).await?;
+        assert_eq!(
+            Cursor::retrieve(&storage, RANKED_CURSOR, false)
+                .await?
+                .version,
+            3
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_s3_cursor() -> Result<(), Box<dyn Error>> {
+        let storage = empty_s3_client().await?;
+        let cursor = Cursor::retrieve(&storage, RANKED_CURSOR, false).await?;
+        assert_eq!(cursor.approved_date, 0);
+        assert!(cursor.cursor_string.is_none());
+        assert!(cursor.tag.is_none());
+        assert_eq!(Cursor::retrieve(&storage, "loved", false).await?.version, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_cursor() -> Result<(), Box<dyn Error>> {
+        let storage = Client::new(MemoryBackend::default());
+        storage.upload(b"{", "cursors/qualified.json").await?;
+        assert!(matches!(
+            Cursor::retrieve(&storage, "qualified", false).await,
+            Err(StorageError::CorruptCursor { .. })
+        ));
+        let mut cursor = Cursor::retrieve(&storage, "qualified", true).await?;
+        assert_eq!(cursor.approved_date, 0);
+        cursor.update(&storage).await?;
+        assert_eq!(
+            Cursor::retrieve(&storage, "qualified", false)
+                .await?
+                .version,
+            1
+        );
+        storage.upload(b"{", LEGACY_CURSOR_FILE).await?;
+        assert!(Cursor::retrieve(&storage, RANKED_CURSOR, false)
+            .await
+            .is_err());
+        assert!(Cursor::retrieve(&storage, RANKED_CURSOR, true)
+            .await
+            .is_ok());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_legacy_cursor() -> Result<(), Box<dyn Error>> {
+        let storage = Client::new(MemoryBackend::default());
+        storage
+            .upload(br#"{"approved_date":1,"id":74}"#, LEGACY_CURSOR_FILE)
             .await?;
+        let mut cursor = Cursor::retrieve(&storage, RANKED_CURSOR, false).await?;
+        assert_eq!(cursor.approved_date, 1);
+        assert!(cursor.cursor_string.is_some());
+        assert_eq!(cursor.version, 0);
+        cursor.approved_date = 2;
+        cursor.update(&storage).await?;
+        let cursor = Cursor::retrieve(&storage, RANKED_CURSOR, false).await?;
+        assert_eq!(cursor.approved_date, 2);
+        assert!(Cursor::retrieve(&storage, "loved", false)
+            .await?
+            .cursor_string
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_cursor() -> Result<(), Box<dyn Error>> {
+        let cursor = Cursor::parse(br#"{"approved_date":1192064884000,"id":74}"#, RANKED_CURSOR)?;
+        assert_eq!(cursor.approved_date, 1192064884000);
+        assert_eq!(
+            cursor.cursor_string.as_deref(),
+            Some("eyJhcHByb3ZlZF9kYXRlIjoiMTE5MjA2NDg4NDAwMCIsImlkIjoiNzQifQ==")
+        );
+        let cursor = Cursor::parse(
+            &serde_json::to_vec(&Cursor::new(1, None, RANKED_CURSOR))?,
+            RANKED_CURSOR,
+        )?;
+        assert_eq!(cursor.approved_date, 1);
+        assert!(cursor.cursor_string.is_none());
+        assert!(matches!(
+            Cursor::parse(b"[]", RANKED_CURSOR),
+            Err(StorageError::CorruptCursor { .. })
+        ));
         Ok(())
     }
 }
@@ -16,9 +16,18 @@ enum CollectionFile {
 	BPMDB,
 	BPMOSDB,
 	CollectionDB,
-	CollectionOSDB
+	CollectionOSDB,
+	ListCSV,
+	ListJSON,
+	ListLinks
 }
 
+const listFormats: Partial<Record<CollectionFile, [string, string]>> = {
+	[CollectionFile.ListCSV]: ['csv', 'csv'],
+	[CollectionFile.ListJSON]: ['json', 'json'],
+	[CollectionFile.ListLinks]: ['links', 'txt']
+};
+
 export default function BeatmapsSearchBar({
 	queryFilters,
 	queryOrder,
@@ -55,13 +64,18 @@ export default function BeatmapsSearchBar({
 		const option = parseInt(target.value);
 		const use_osdb_format =
 			option === CollectionFile.BPMOSDB || option === CollectionFile.CollectionOSDB;
+		const listFormat = listFormats[option as CollectionFile];
 		target.value = '';
 		const response = await fetch(`/api/beatmap/collection`, {
 			method: 'POST',
 			body: JSON.stringify({
 				filters: parseQuery(queryFilters),
 				title: queryTitle,
-				use_bpm_division: option === CollectionFile.BPMDB || option === CollectionFile.BPMOSDB,
+				groupings:
+					option === CollectionFile.BPMDB || option === CollectionFile.BPMOSDB
+						? [{ type: 'bpm', width: 10 }]
+						: [],
+				list_format: listFormat?.[0],
 				use_osdb_format
 			}),
 			headers: {
@@ -73,7 +87,7 @@ export default function BeatmapsSearchBar({
 		const url = window.URL.createObjectURL(await response.blob());
 		const a = document.createElement('a');
 		a.href = url;
-		a.download = `Collection.${use_osdb_format ? 'osdb' : 'db'}`;
+		a.download = `Collection.${listFormat?.[1] ?? (use_osdb_format ? 'osdb' : 'db')}`;
 		document.body.appendChild(a);
 		a.style.display = 'none';
 		a.click();
@@ -139,6 +153,9 @@ export default function BeatmapsSearchBar({
 					<option value={CollectionFile.CollectionOSDB}>{t('download_osdb_single')}</option>
 					<option value={CollectionFile.BPMDB}>{t('download_db_divided')}</option>
 					<option value={CollectionFile.BPMOSDB}>{t('download_osdb_divided')}</option>
+					<option value={CollectionFile.ListCSV}>{t('download_list_csv')}</option>
+					<option value={CollectionFile.ListJSON}>{t('download_list_json')}</option>
+					<option value={CollectionFile.ListLinks}>{t('download_list_links')}</option>
 				</select>
 			</div>
 		</div>